@@ -1,12 +1,13 @@
 use crate::error::GameSyncError;
 use crate::error::GameSyncError::LockError;
+use crate::lobby::Region;
 use crate::networking::{ClientEvent, Websocket};
 use crate::server_events::ServerEvent;
 use crate::store::Store;
 use message_io::network::SendStatus;
 use std::sync::{Arc, Mutex};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use uuid::Uuid;
 
 
@@ -23,6 +24,12 @@ pub trait MessageHandler {
 
 impl GameSyncClient {
     pub fn connect(url: &str) -> Result<Self, GameSyncError> {
+        Self::connect_within(url, None)
+    }
+
+    // Gives up with TimeoutError if the connection is not set up within timeout. None waits for as long as it takes
+    fn connect_within(url: &str, timeout: Option<Duration>) -> Result<Self, GameSyncError> {
+        let started = Instant::now();
         let store = Arc::new(Mutex::new(Store::new()));
         let store_clone = Arc::clone(&store);
 
@@ -34,15 +41,15 @@ impl GameSyncClient {
         let (websocket, node_task) = Websocket::new(url, event_handler)?;
 
         loop {
-            let store = store.lock();
-            match store {
-                Ok(mut store) => {
-                    if store.is_connected != false {
-                        store.node_task = Some(node_task);
-                        break;
-                    }
+            if let Ok(mut store) = store.lock() {
+                if store.is_connected {
+                    store.node_task = Some(node_task);
+                    break;
                 }
-                Err(_) => {}
+            }
+            if timeout.is_some_and(|timeout| started.elapsed() >= timeout) {
+                websocket.stop();
+                return Err(GameSyncError::TimeoutError)
             }
 
             thread::sleep(Duration::from_millis(100));
@@ -83,4 +90,61 @@ impl GameSyncClient {
             Err(_) => { Err(GameSyncError::LockError) }
         }
     }
+
+    pub fn ping(&mut self) -> Result<SendStatus, GameSyncError> {
+        let ping_id = match self.store.lock() {
+            Ok(mut store) => { store.start_ping() }
+            Err(_) => { return Err(LockError) }
+        };
+        self.websocket.send_event(ClientEvent::Ping(ping_id))
+    }
+
+    // Round trip time of the last answered ping
+    pub fn get_rtt(&self) -> Result<Option<Duration>, GameSyncError> {
+        match self.store.lock() {
+            Ok(store) => { Ok(store.get_rtt()) }
+            Err(_) => { Err(LockError) }
+        }
+    }
+
+    // Regions the server advertised in its last Pong
+    pub fn get_server_regions(&self) -> Result<Vec<Region>, GameSyncError> {
+        match self.store.lock() {
+            Ok(store) => { Ok(store.get_server_regions()) }
+            Err(_) => { Err(LockError) }
+        }
+    }
+
+    // Connects to each server, pings it once and returns the first region served by the server with the lowest RTT.
+    // Servers that can not be reached, do not connect and answer within the timeout or do not advertise any region are skipped
+    pub fn find_best_region(urls: &[&str], timeout: Duration) -> Result<Option<(Region, Duration)>, GameSyncError> {
+        let mut best: Option<(Region, Duration)> = None;
+        for url in urls {
+            let started = Instant::now();
+            let mut client = match Self::connect_within(url, Some(timeout)) {
+                Ok(client) => client,
+                Err(_) => continue
+            };
+            let mut result = None;
+            let sent = matches!(client.ping(), Ok(SendStatus::Sent));
+            while sent && started.elapsed() < timeout {
+                if let Ok(Some(rtt)) = client.get_rtt() {
+                    result = client.get_server_regions().ok().and_then(|regions| regions.first().map(|region| (region.clone(), rtt)));
+                    break;
+                }
+                thread::sleep(Duration::from_millis(10));
+            }
+            // The node thread can not join itself, so the task is dropped here once the node stops
+            let node_task = client.store.lock().ok().and_then(|mut store| store.node_task.take());
+            client.websocket.stop();
+            drop(node_task);
+
+            if let Some((region, rtt)) = result {
+                if best.as_ref().is_none_or(|(_, best_rtt)| rtt < *best_rtt) {
+                    best = Some((region, rtt));
+                }
+            }
+        }
+        Ok(best)
+    }
 }
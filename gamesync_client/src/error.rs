@@ -10,6 +10,7 @@ pub enum GameSyncError {
     UuidError(UuidError),
     LockError,
     SendError,
+    TimeoutError,
 }
 
 impl From<ParseError> for GameSyncError {
@@ -38,6 +39,7 @@ impl fmt::Display for GameSyncError {
             GameSyncError::SendError => write!(f, "Failed to send socket event"),
             GameSyncError::UuidError(err) => write!(f, "Failed to parse uuid: {}", err),
             GameSyncError::LockError => write!(f, "Failed to access store"),
+            GameSyncError::TimeoutError => write!(f, "Timed out connecting to the server"),
         }
     }
 }
@@ -1,9 +1,11 @@
 use crate::client::GameSyncClient;
 use crate::error::GameSyncError;
 use crate::networking::ClientEvent;
-use crate::store::{LobbyID, PlayerID};
+use crate::store::{LobbyID, MatchID, PlayerID};
 use message_io::network::SendStatus;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime};
 use uuid::Uuid;
 
 
@@ -15,6 +17,8 @@ pub struct Lobby {
     pub status: LobbyStatus,
     pub player_list: Vec<PlayerID>,
     pub queue_threshold: usize,
+    #[serde(default)]
+    pub parties: Vec<LobbyID>, // Lobbies a server-assembled team was formed from. Empty for regular lobbies
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -23,12 +27,45 @@ pub struct LobbyParams {
     pub visibility: Visibility,
     pub region: Region,
     pub mode: GameMode,
+    #[serde(default)]
+    pub fallback_regions: Vec<Region>,
+    #[serde(default)]
+    pub fallback_delay: Duration,
+    #[serde(default)]
+    pub hard_tags: HashMap<String, String>, // Matchmaking tags the opponent must not contradict, e.g. ruleset=hardcore
+    #[serde(default)]
+    pub soft_tags: HashMap<String, String>, // Preferred matchmaking tags, e.g. map=desert
 }
 
-#[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct Player {
     pub player_id: Uuid,
     pub rating: usize,
+    pub profile: PlayerProfile,
+    #[serde(default)]
+    pub role_preferences: Vec<String>, // Ranked, most preferred first. Empty to fill any role
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct PlayerProfile {
+    #[serde(default)]
+    pub display_name: String, // Empty until the player sets one
+    #[serde(default)]
+    pub avatar_id: Option<String>,
+    pub metadata: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PlayerSummary {
+    pub player_id: PlayerID,
+    pub display_name: String,
+    pub avatar_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum Credentials {
+    Token(String),
+    Password { username: String, password: String },
 }
 
 #[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq)]
@@ -38,17 +75,27 @@ pub enum Visibility {
 }
 
 
-#[derive(Eq, Hash, PartialEq, Copy, Debug, Clone, Serialize, Deserialize)]
-pub enum Region {
-    NA,
-    EU,
-    SA,
-    MEA,
-    AS,
-    AU,
+#[derive(Eq, Hash, PartialEq, Debug, Clone, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Region(String); // Region identifier, e.g. "us-east". Must be one of the regions configured on the server
+
+impl Region {
+    pub fn new(id: &str) -> Self {
+        Region(id.to_string())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<&str> for Region {
+    fn from(id: &str) -> Self {
+        Region::new(id)
+    }
 }
 
-#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+#[derive(Eq, Hash, PartialEq, Copy, Debug, Clone, Serialize, Deserialize)]
 pub enum GameMode {
     Casual,
     Competitive,
@@ -58,9 +105,135 @@ pub enum GameMode {
 pub enum LobbyStatus {
     Idle,
     Queueing,
+    ReadyCheck,
     Ingame,
 }
 
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq)]
+pub enum MatchOutcome {
+    Win,
+    Loss,
+    Draw,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MatchInfo {
+    pub match_id: MatchID,
+    pub region: Region,
+    pub opponent: Lobby,
+    #[serde(default)]
+    pub role: Option<String>, // Role assigned to this player. None if the server has no role composition
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueueStatus {
+    pub lobby_id: LobbyID,
+    pub time_in_queue: Duration,
+    pub position: usize,
+    pub lobbies_in_queue: usize,
+    pub estimated_wait: Option<Duration>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Rank {
+    pub tier: String,
+    pub division: usize, // 1 is the highest division of the tier
+    pub progress: Option<usize>, // Percent of the way to the next division. None in the highest tier
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SeasonResult {
+    pub season: usize,
+    pub rating: usize,
+    pub rank: Option<Rank>, // None if the placement matches were not finished
+    pub matches_played: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RankInfo {
+    pub player_id: PlayerID,
+    pub season: usize,
+    pub rank: Option<Rank>, // None until the placement matches are played
+    pub placement_matches_left: usize,
+    pub season_history: Vec<SeasonResult>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LeaderboardEntry {
+    pub position: usize, // 1 is the highest rated player
+    pub player_id: PlayerID,
+    pub rating: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Leaderboard {
+    pub region: Region,
+    pub mode: GameMode,
+    pub total_players: usize,
+    pub entries: Vec<LeaderboardEntry>,
+}
+
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq)]
+pub enum Presence {
+    Offline,
+    Online, // Connected but not in a lobby
+    InLobby,
+    Queueing, // Queueing or in a ready check
+    InGame,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FriendInfo {
+    pub player_id: PlayerID,
+    pub presence: Presence,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FriendList {
+    pub friends: Vec<FriendInfo>,
+    pub incoming_requests: Vec<PlayerID>, // Players who sent a friend request to this player
+    pub outgoing_requests: Vec<PlayerID>, // Players this player sent a friend request to
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DirectMessage {
+    pub message_id: u64, // Pass as `before` to get_conversation to load older messages
+    pub from: PlayerID,
+    pub to: PlayerID,
+    pub message: String,
+    pub sent_at: SystemTime,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum ChannelKind {
+    Global,
+    Regional(Region),
+    Guild(String), // Only joinable by players whose "guild" claim matches
+    Custom, // Created by a player and deleted when its last member leaves
+}
+
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq)]
+pub enum PostPermission {
+    Members,
+    Moderators,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Channel {
+    pub name: String,
+    pub kind: ChannelKind,
+    pub post: PostPermission,
+    pub owner: Option<PlayerID>,
+    pub moderators: Vec<PlayerID>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LobbyChatMessage {
+    pub from: PlayerSummary,
+    pub message: String,
+    pub sent_at: SystemTime,
+}
+
 impl GameSyncClient {
     pub fn create_lobby(&mut self, params: LobbyParams) -> Result<SendStatus, GameSyncError>
     {
@@ -98,9 +271,15 @@ impl GameSyncClient {
         Ok(result)
     }
 
-    pub fn edit_player(&mut self, player: Player) -> Result<SendStatus, GameSyncError>
+    pub fn edit_player(&mut self, profile: PlayerProfile) -> Result<SendStatus, GameSyncError>
     {
-        let result = self.websocket.send_event(ClientEvent::EditPlayer(player))?;
+        let result = self.websocket.send_event(ClientEvent::EditPlayer(profile))?;
+        Ok(result)
+    }
+
+    pub fn set_role_preferences(&mut self, roles: Vec<String>) -> Result<SendStatus, GameSyncError>
+    {
+        let result = self.websocket.send_event(ClientEvent::SetRolePreferences(roles))?;
         Ok(result)
     }
 
@@ -139,4 +318,178 @@ impl GameSyncClient {
         let result = self.websocket.send_event(ClientEvent::GetLobbyInfo(lobby_id))?;
         Ok(result)
     }
+
+    pub fn report_match_result(&mut self, match_id: MatchID, outcome: MatchOutcome) -> Result<SendStatus, GameSyncError>
+    {
+        let result = self.websocket.send_event(ClientEvent::ReportMatchResult(match_id, outcome))?;
+        Ok(result)
+    }
+
+    pub fn accept_match(&mut self, match_id: MatchID) -> Result<SendStatus, GameSyncError>
+    {
+        let result = self.websocket.send_event(ClientEvent::AcceptMatch(match_id))?;
+        Ok(result)
+    }
+
+    pub fn decline_match(&mut self, match_id: MatchID) -> Result<SendStatus, GameSyncError>
+    {
+        let result = self.websocket.send_event(ClientEvent::DeclineMatch(match_id))?;
+        Ok(result)
+    }
+
+    pub fn get_queue_status(&mut self, lobby_id: LobbyID) -> Result<SendStatus, GameSyncError>
+    {
+        let result = self.websocket.send_event(ClientEvent::GetQueueStatus(lobby_id))?;
+        Ok(result)
+    }
+
+    pub fn get_rank_info(&mut self, player_id: PlayerID) -> Result<SendStatus, GameSyncError>
+    {
+        let result = self.websocket.send_event(ClientEvent::GetRankInfo(player_id))?;
+        Ok(result)
+    }
+
+    pub fn get_leaderboard(&mut self, region: Region, mode: GameMode, offset: usize, limit: usize) -> Result<SendStatus, GameSyncError>
+    {
+        let result = self.websocket.send_event(ClientEvent::GetLeaderboard(region, mode, offset, limit))?;
+        Ok(result)
+    }
+
+    pub fn get_my_leaderboard_position(&mut self, region: Region, mode: GameMode) -> Result<SendStatus, GameSyncError>
+    {
+        let result = self.websocket.send_event(ClientEvent::GetMyLeaderboardPosition(region, mode))?;
+        Ok(result)
+    }
+
+    pub fn send_friend_request(&mut self, player_id: PlayerID) -> Result<SendStatus, GameSyncError>
+    {
+        let result = self.websocket.send_event(ClientEvent::SendFriendRequest(player_id))?;
+        Ok(result)
+    }
+
+    pub fn accept_friend_request(&mut self, player_id: PlayerID) -> Result<SendStatus, GameSyncError>
+    {
+        let result = self.websocket.send_event(ClientEvent::AcceptFriendRequest(player_id))?;
+        Ok(result)
+    }
+
+    pub fn decline_friend_request(&mut self, player_id: PlayerID) -> Result<SendStatus, GameSyncError>
+    {
+        let result = self.websocket.send_event(ClientEvent::DeclineFriendRequest(player_id))?;
+        Ok(result)
+    }
+
+    pub fn remove_friend(&mut self, player_id: PlayerID) -> Result<SendStatus, GameSyncError>
+    {
+        let result = self.websocket.send_event(ClientEvent::RemoveFriend(player_id))?;
+        Ok(result)
+    }
+
+    pub fn get_friends(&mut self) -> Result<SendStatus, GameSyncError>
+    {
+        let result = self.websocket.send_event(ClientEvent::GetFriends)?;
+        Ok(result)
+    }
+
+    pub fn block_player(&mut self, player_id: PlayerID) -> Result<SendStatus, GameSyncError>
+    {
+        let result = self.websocket.send_event(ClientEvent::BlockPlayer(player_id))?;
+        Ok(result)
+    }
+
+    pub fn unblock_player(&mut self, player_id: PlayerID) -> Result<SendStatus, GameSyncError>
+    {
+        let result = self.websocket.send_event(ClientEvent::UnblockPlayer(player_id))?;
+        Ok(result)
+    }
+
+    pub fn login(&mut self, credentials: Credentials) -> Result<SendStatus, GameSyncError>
+    {
+        let result = self.websocket.send_event(ClientEvent::Login(credentials))?;
+        Ok(result)
+    }
+
+    pub fn authenticate(&mut self, token: String) -> Result<SendStatus, GameSyncError>
+    {
+        let result = self.websocket.send_event(ClientEvent::Authenticate { token })?;
+        Ok(result)
+    }
+
+    pub fn get_profiles(&mut self, player_ids: Vec<PlayerID>) -> Result<SendStatus, GameSyncError>
+    {
+        let result = self.websocket.send_event(ClientEvent::GetProfiles(player_ids))?;
+        Ok(result)
+    }
+
+    pub fn get_conversation(&mut self, with: PlayerID, before: Option<u64>, limit: usize) -> Result<SendStatus, GameSyncError>
+    {
+        let result = self.websocket.send_event(ClientEvent::GetConversation { with, before, limit })?;
+        Ok(result)
+    }
+
+    pub fn get_lobby_chat_history(&mut self, lobby_id: LobbyID) -> Result<SendStatus, GameSyncError>
+    {
+        let result = self.websocket.send_event(ClientEvent::GetLobbyChatHistory(lobby_id))?;
+        Ok(result)
+    }
+
+    pub fn create_channel(&mut self, name: String, post: PostPermission) -> Result<SendStatus, GameSyncError>
+    {
+        let result = self.websocket.send_event(ClientEvent::CreateChannel(name, post))?;
+        Ok(result)
+    }
+
+    pub fn join_channel(&mut self, name: String) -> Result<SendStatus, GameSyncError>
+    {
+        let result = self.websocket.send_event(ClientEvent::JoinChannel(name))?;
+        Ok(result)
+    }
+
+    pub fn leave_channel(&mut self, name: String) -> Result<SendStatus, GameSyncError>
+    {
+        let result = self.websocket.send_event(ClientEvent::LeaveChannel(name))?;
+        Ok(result)
+    }
+
+    pub fn message_channel(&mut self, name: String, message: String) -> Result<SendStatus, GameSyncError>
+    {
+        let result = self.websocket.send_event(ClientEvent::MessageChannel(name, message))?;
+        Ok(result)
+    }
+
+    pub fn kick_from_channel(&mut self, name: String, player_id: PlayerID) -> Result<SendStatus, GameSyncError>
+    {
+        let result = self.websocket.send_event(ClientEvent::KickFromChannel(name, player_id))?;
+        Ok(result)
+    }
+
+    pub fn set_channel_moderator(&mut self, name: String, player_id: PlayerID, moderator: bool) -> Result<SendStatus, GameSyncError>
+    {
+        let result = self.websocket.send_event(ClientEvent::SetChannelModerator(name, player_id, moderator))?;
+        Ok(result)
+    }
+
+    pub fn get_channels(&mut self) -> Result<SendStatus, GameSyncError>
+    {
+        let result = self.websocket.send_event(ClientEvent::GetChannels)?;
+        Ok(result)
+    }
+
+    pub fn mute_player(&mut self, lobby_id: LobbyID, player_id: PlayerID, duration: Duration) -> Result<SendStatus, GameSyncError>
+    {
+        let result = self.websocket.send_event(ClientEvent::MutePlayer(lobby_id, player_id, duration))?;
+        Ok(result)
+    }
+
+    pub fn unmute_player(&mut self, lobby_id: LobbyID, player_id: PlayerID) -> Result<SendStatus, GameSyncError>
+    {
+        let result = self.websocket.send_event(ClientEvent::UnmutePlayer(lobby_id, player_id))?;
+        Ok(result)
+    }
+
+    pub fn report_player(&mut self, player_id: PlayerID, reason: String) -> Result<SendStatus, GameSyncError>
+    {
+        let result = self.websocket.send_event(ClientEvent::ReportPlayer(player_id, reason))?;
+        Ok(result)
+    }
 }
\ No newline at end of file
@@ -1,11 +1,12 @@
 use crate::error::GameSyncError::ParseError;
 use crate::error::{print_error, GameSyncError};
-use crate::lobby::{LobbyParams, Player, Region};
+use crate::lobby::{Credentials, GameMode, LobbyParams, MatchOutcome, PlayerProfile, PostPermission, Region};
 use crate::server_events::ServerEvent;
-use crate::store::{LobbyID, PlayerID};
+use crate::store::{LobbyID, MatchID, PlayerID};
 use message_io::network::{Endpoint, NetEvent, SendStatus, Transport};
 use message_io::node::{self, NodeEvent, NodeHandler, NodeTask};
 use serde::{Deserialize, Serialize};
+use std::time::Duration;
 // Sync these with server-side enum
 
 #[derive(Serialize, Deserialize)]
@@ -19,13 +20,44 @@ pub enum ClientEvent {
     LeaveLobby(LobbyID), // async wait for LobbyLeft
     InviteLobby(LobbyID, PlayerID),  // Sender ID, Lobby ID, Invitee ID
     GetPublicLobbies(Region),
-    EditPlayer(Player),
+    EditPlayer(PlayerProfile),
     MessageLobby(LobbyID, String), // Sender ID, Lobby ID, Message
     QueueLobby(LobbyID),
     CheckMatch(LobbyID, Option<usize>), // Sender ID, Lobby ID, Threshold
     StopQueue(LobbyID),
     LeaveGameAsLobby(LobbyID),
     GetLobbyInfo(LobbyID),
+    ReportMatchResult(MatchID, MatchOutcome),
+    AcceptMatch(MatchID),
+    DeclineMatch(MatchID),
+    GetQueueStatus(LobbyID),
+    Ping(u64), // Ping ID
+    SetRolePreferences(Vec<String>), // Ranked, most preferred first
+    GetRankInfo(PlayerID),
+    GetLeaderboard(Region, GameMode, usize, usize), // Region, Mode, Offset, Limit
+    GetMyLeaderboardPosition(Region, GameMode),
+    SendFriendRequest(PlayerID), // Target ID
+    AcceptFriendRequest(PlayerID), // Sender ID
+    DeclineFriendRequest(PlayerID), // Sender ID
+    RemoveFriend(PlayerID), // Friend ID
+    GetFriends,
+    BlockPlayer(PlayerID),
+    UnblockPlayer(PlayerID),
+    Login(Credentials),
+    Authenticate { token: String },
+    GetProfiles(Vec<PlayerID>),
+    GetConversation { with: PlayerID, before: Option<u64>, limit: usize },
+    GetLobbyChatHistory(LobbyID),
+    CreateChannel(String, PostPermission),
+    JoinChannel(String),
+    LeaveChannel(String),
+    MessageChannel(String, String),
+    KickFromChannel(String, PlayerID),
+    SetChannelModerator(String, PlayerID, bool),
+    GetChannels,
+    MutePlayer(LobbyID, PlayerID, Duration),
+    UnmutePlayer(LobbyID, PlayerID),
+    ReportPlayer(PlayerID, String),
 }
 
 #[derive(Clone)]
@@ -51,6 +83,10 @@ impl Websocket {
         Ok((websocket, node_task))
     }
 
+    pub fn stop(&self) {
+        self.handler.stop();
+    }
+
     pub fn send_event(&mut self, event: ClientEvent) -> Result<SendStatus, GameSyncError> {
         let payload = serde_json::to_string(&event)?;
         Ok(self.handler.network().send(self.server, payload.as_ref()))
@@ -123,8 +159,8 @@ impl ServerConnection {
                     ServerEvent::LobbyQueued(id) => {
                         send_event(ServerEvent::LobbyQueued(id));
                     }
-                    ServerEvent::MatchFound(lobby) => {
-                        send_event(ServerEvent::MatchFound(lobby));
+                    ServerEvent::MatchFound(match_info) => {
+                        send_event(ServerEvent::MatchFound(match_info));
                     }
                     ServerEvent::MatchNotFound => {
                         send_event(ServerEvent::MatchNotFound);
@@ -135,8 +171,122 @@ impl ServerConnection {
                     ServerEvent::LeftGame(lobbies) => {
                         send_event(ServerEvent::LeftGame(lobbies));
                     }
-                    ServerEvent::LobbyInfo(id) => {
-                        send_event(ServerEvent::LobbyInfo(id));
+                    ServerEvent::LobbyInfo(lobby, roster) => {
+                        send_event(ServerEvent::LobbyInfo(lobby, roster));
+                    }
+                    ServerEvent::RatingChanged(old, new) => {
+                        send_event(ServerEvent::RatingChanged(old, new));
+                    }
+                    ServerEvent::MatchResultDisputed(id) => {
+                        send_event(ServerEvent::MatchResultDisputed(id));
+                    }
+                    ServerEvent::MatchProposed(id, deadline) => {
+                        send_event(ServerEvent::MatchProposed(id, deadline));
+                    }
+                    ServerEvent::MatchAccepted(id, player_id) => {
+                        send_event(ServerEvent::MatchAccepted(id, player_id));
+                    }
+                    ServerEvent::MatchCancelled(id) => {
+                        send_event(ServerEvent::MatchCancelled(id));
+                    }
+                    ServerEvent::QueueStatus(status) => {
+                        send_event(ServerEvent::QueueStatus(status));
+                    }
+                    ServerEvent::Pong(ping_id, regions) => {
+                        send_event(ServerEvent::Pong(ping_id, regions));
+                    }
+                    ServerEvent::TeamFormed(team) => {
+                        send_event(ServerEvent::TeamFormed(team));
+                    }
+                    ServerEvent::TeamDisbanded(team_id) => {
+                        send_event(ServerEvent::TeamDisbanded(team_id));
+                    }
+                    ServerEvent::RankInfo(rank_info) => {
+                        send_event(ServerEvent::RankInfo(rank_info));
+                    }
+                    ServerEvent::SeasonStarted(season) => {
+                        send_event(ServerEvent::SeasonStarted(season));
+                    }
+                    ServerEvent::Leaderboard(leaderboard) => {
+                        send_event(ServerEvent::Leaderboard(leaderboard));
+                    }
+                    ServerEvent::LeaderboardPosition(region, mode, entry) => {
+                        send_event(ServerEvent::LeaderboardPosition(region, mode, entry));
+                    }
+                    ServerEvent::QueueBanned(until) => {
+                        send_event(ServerEvent::QueueBanned(until));
+                    }
+                    ServerEvent::BackfillJoined(match_id, lobby_id, player_id) => {
+                        send_event(ServerEvent::BackfillJoined(match_id, lobby_id, player_id));
+                    }
+                    ServerEvent::FriendRequestReceived(sender_id) => {
+                        send_event(ServerEvent::FriendRequestReceived(sender_id));
+                    }
+                    ServerEvent::FriendAdded(friend) => {
+                        send_event(ServerEvent::FriendAdded(friend));
+                    }
+                    ServerEvent::FriendRemoved(friend_id) => {
+                        send_event(ServerEvent::FriendRemoved(friend_id));
+                    }
+                    ServerEvent::FriendPresenceChanged(friend_id, presence) => {
+                        send_event(ServerEvent::FriendPresenceChanged(friend_id, presence));
+                    }
+                    ServerEvent::Friends(friend_list) => {
+                        send_event(ServerEvent::Friends(friend_list));
+                    }
+                    ServerEvent::PlayerBlocked(player_id) => {
+                        send_event(ServerEvent::PlayerBlocked(player_id));
+                    }
+                    ServerEvent::PlayerUnblocked(player_id) => {
+                        send_event(ServerEvent::PlayerUnblocked(player_id));
+                    }
+                    ServerEvent::LoggedIn(player_id) => {
+                        send_event(ServerEvent::LoggedIn(player_id));
+                    }
+                    ServerEvent::Authenticated(player_id, claims) => {
+                        send_event(ServerEvent::Authenticated(player_id, claims));
+                    }
+                    ServerEvent::AuthRejected(reason) => {
+                        send_event(ServerEvent::AuthRejected(reason));
+                    }
+                    ServerEvent::Profiles(profiles) => {
+                        send_event(ServerEvent::Profiles(profiles));
+                    }
+                    ServerEvent::Conversation(with, messages) => {
+                        send_event(ServerEvent::Conversation(with, messages));
+                    }
+                    ServerEvent::OfflineMessages(messages) => {
+                        send_event(ServerEvent::OfflineMessages(messages));
+                    }
+                    ServerEvent::LobbyChatHistory(lobby_id, messages) => {
+                        send_event(ServerEvent::LobbyChatHistory(lobby_id, messages));
+                    }
+                    ServerEvent::ChannelJoined(channel) => {
+                        send_event(ServerEvent::ChannelJoined(channel));
+                    }
+                    ServerEvent::ChannelLeft(name) => {
+                        send_event(ServerEvent::ChannelLeft(name));
+                    }
+                    ServerEvent::ChannelMessage(name, from, message) => {
+                        send_event(ServerEvent::ChannelMessage(name, from, message));
+                    }
+                    ServerEvent::ChannelUpdated(channel) => {
+                        send_event(ServerEvent::ChannelUpdated(channel));
+                    }
+                    ServerEvent::Channels(channels) => {
+                        send_event(ServerEvent::Channels(channels));
+                    }
+                    ServerEvent::MessageRejected(reason) => {
+                        send_event(ServerEvent::MessageRejected(reason));
+                    }
+                    ServerEvent::PlayerMuted(lobby_id, player_id, until) => {
+                        send_event(ServerEvent::PlayerMuted(lobby_id, player_id, until));
+                    }
+                    ServerEvent::PlayerUnmuted(lobby_id, player_id) => {
+                        send_event(ServerEvent::PlayerUnmuted(lobby_id, player_id));
+                    }
+                    ServerEvent::PlayerReported(player_id) => {
+                        send_event(ServerEvent::PlayerReported(player_id));
                     }
                     _ => {}
                 }
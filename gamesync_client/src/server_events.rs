@@ -1,27 +1,67 @@
-use crate::lobby::Lobby;
-use crate::store::{LobbyID, PlayerID};
+use crate::lobby::{Channel, DirectMessage, FriendInfo, FriendList, GameMode, Leaderboard, LeaderboardEntry, Lobby, LobbyChatMessage, MatchInfo, PlayerProfile, PlayerSummary, Presence, QueueStatus, RankInfo, Region};
+use crate::store::{LobbyID, MatchID, PlayerID};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::SystemTime;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 // server events from non-client initiated events - use event callbacks
 // if client-initiated,
 pub enum ServerEvent {
     Connected(),
-    UserMessage(PlayerID, String),
+    UserMessage(PlayerSummary, String), // From, Msg
     SelfPlayer(String),
     NewPlayer(String),
     LobbyCreated(Lobby), // Lobby
-    LobbyJoined(PlayerID, LobbyID), // Lobby ID
+    LobbyJoined(PlayerSummary, LobbyID), // Player who joined, Lobby ID
     LobbyDeleted(LobbyID), // Lobby ID
     LobbyLeft(PlayerID, LobbyID), // Lobby ID
     LobbyInvited(LobbyID), // Lobby ID
     PublicLobbies(Vec<Lobby>),
     PlayerEdited(PlayerID), // Player IDConnected(),
-    LobbyMessage(PlayerID, String), // Msg
+    LobbyMessage(PlayerSummary, String), // From, Msg
     LobbyQueued(LobbyID),
-    MatchFound(Lobby), // Opponent lobby
+    MatchFound(MatchInfo), // Match ID, Opponent lobby
     MatchNotFound,
     QueueStopped(LobbyID),
     LeftGame(LobbyID),
-    LobbyInfo(Lobby),
+    LobbyInfo(Lobby, Vec<PlayerSummary>), // Lobby, Roster
+    RatingChanged(usize, usize), // Old, New
+    MatchResultDisputed(MatchID),
+    MatchProposed(MatchID, SystemTime), // Match ID, Deadline to accept
+    MatchAccepted(MatchID, PlayerID), // Match ID, Player who accepted
+    MatchCancelled(MatchID),
+    QueueStatus(QueueStatus),
+    Pong(u64, Vec<Region>), // Echoed ping ID, Regions served by the server
+    TeamFormed(Lobby), // Temporary team lobby assembled from partial lobbies
+    TeamDisbanded(LobbyID), // Team lobby ID. Players are back in the lobby they queued with
+    RankInfo(RankInfo),
+    SeasonStarted(usize), // New season number. Ratings have been soft reset
+    Leaderboard(Leaderboard),
+    LeaderboardPosition(Region, GameMode, Option<LeaderboardEntry>), // None if the player is not on this leaderboard
+    QueueBanned(SystemTime), // Lockout end after a dodge or abandon
+    BackfillJoined(MatchID, LobbyID, PlayerID), // Match ID, Lobby the player was added to, Player ID
+    FriendRequestReceived(PlayerID), // Sender ID
+    FriendAdded(FriendInfo),
+    FriendRemoved(PlayerID), // Former friend ID
+    FriendPresenceChanged(PlayerID, Presence), // Friend ID, New presence
+    Friends(FriendList),
+    PlayerBlocked(PlayerID),
+    PlayerUnblocked(PlayerID),
+    LoggedIn(PlayerID), // Persistent player ID of the account. Replaces the ID sent in SelfPlayer
+    Authenticated(PlayerID, HashMap<String, String>), // Player ID, Claims
+    AuthRejected(String), // Reason. The server closes the connection
+    Profiles(Vec<(PlayerID, PlayerProfile)>),
+    Conversation(PlayerID, Vec<DirectMessage>), // Other player, Messages oldest first
+    OfflineMessages(Vec<DirectMessage>), // Direct messages received while offline, oldest first
+    LobbyChatHistory(LobbyID, Vec<LobbyChatMessage>), // Lobby ID, Recent messages oldest first
+    ChannelJoined(Channel),
+    ChannelLeft(String), // Channel name. Also sent when kicked or when the server removes the channel
+    ChannelMessage(String, PlayerSummary, String), // Channel name, From, Msg
+    ChannelUpdated(Channel), // Moderators changed
+    Channels(Vec<Channel>), // Channels the player can join
+    MessageRejected(String), // Reason a chat message from this player was dropped by the server's filters or a mute
+    PlayerMuted(LobbyID, PlayerID, SystemTime), // Lobby ID, Muted player, Mute end
+    PlayerUnmuted(LobbyID, PlayerID),
+    PlayerReported(PlayerID), // Reported player. The report was filed
 }
\ No newline at end of file
@@ -1,18 +1,26 @@
 use crate::client::MessageHandler;
 use crate::error::{print_error, GameSyncError};
+use crate::lobby::Region;
 use crate::server_events::ServerEvent;
 use message_io::node::NodeTask;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
 use uuid::Uuid;
 
 pub type PlayerID = Uuid;
 pub type LobbyID = Uuid;
+pub type MatchID = Uuid;
 
 pub struct Store {
     pub is_connected: bool,
     player_id: PlayerID, // can change to a user struct later
     // callbacks: Arc<Mutex<HashMap<String, Box<dyn Fn(ServerEvent) + Send>>>>,
     pub callbacks: Option<Box<dyn MessageHandler + Send + 'static>>,
-    pub node_task: Option<NodeTask>
+    pub node_task: Option<NodeTask>,
+    next_ping_id: u64,
+    pending_pings: HashMap<u64, Instant>, // Ping ID, Time sent
+    last_rtt: Option<Duration>,
+    server_regions: Vec<Region>, // Regions advertised by the server in its last Pong
 }
 
 impl Store {
@@ -22,6 +30,10 @@ impl Store {
             player_id: Uuid::nil(),
             callbacks: None,
             node_task: None,
+            next_ping_id: 0,
+            pending_pings: HashMap::new(),
+            last_rtt: None,
+            server_regions: Vec::new(),
         }
     }
 
@@ -42,6 +54,15 @@ impl Store {
                     Err(error) => { print_error(error) }
                 }
             }
+            ServerEvent::LoggedIn(player_id) | ServerEvent::Authenticated(player_id, _) => {
+                self.player_id = player_id;
+            }
+            ServerEvent::Pong(ping_id, regions) => {
+                if let Some(sent) = self.pending_pings.remove(&ping_id) {
+                    self.last_rtt = Some(sent.elapsed());
+                }
+                self.server_regions = regions;
+            }
             _ => {}
         }
         self.trigger_callback(event);
@@ -61,4 +82,19 @@ impl Store {
         self.player_id = Uuid::parse_str(&player_id)?;
         Ok(())
     }
-}
\ No newline at end of file
+
+    pub fn start_ping(&mut self) -> u64 {
+        let ping_id = self.next_ping_id;
+        self.next_ping_id += 1;
+        self.pending_pings.insert(ping_id, Instant::now());
+        ping_id
+    }
+
+    pub fn get_rtt(&self) -> Option<Duration> {
+        self.last_rtt
+    }
+
+    pub fn get_server_regions(&self) -> Vec<Region> {
+        self.server_regions.clone()
+    }
+}
@@ -0,0 +1,73 @@
+// Runs the matchmaking simulation and prints a report. Compares threshold strategies over the same arrivals
+//
+// cargo run --release --example matchmaking_sim -- lobbies=5000 rate=10 mean=1000 std_dev=250 players=2 mode=competitive
+// Strategies are given as fixed:<threshold> or widening:<initial>,<step>,<interval secs>,<max> and can be repeated
+
+use std::env;
+use std::time::Duration;
+use gamesync_server::lobby::GameMode;
+use gamesync_server::simulation::{run_simulation, RatingDistribution, SimulationParams, ThresholdStrategy};
+
+fn parse_strategy(value: &str) -> Option<ThresholdStrategy> {
+    let (kind, args) = value.split_once(':')?;
+    let args: Vec<u64> = args.split(',').map(|arg| arg.trim().parse().ok()).collect::<Option<_>>()?;
+    match (kind, args.as_slice()) {
+        ("fixed", [threshold]) => Some(ThresholdStrategy::Fixed(*threshold as usize)),
+        ("widening", [initial, step, interval, max]) => Some(ThresholdStrategy::Widening {
+            initial: *initial as usize,
+            step: *step as usize,
+            interval: Duration::from_secs(*interval),
+            max: *max as usize,
+        }),
+        _ => None
+    }
+}
+
+fn main() {
+    let mut params = SimulationParams::default();
+    let (mut mean, mut std_dev) = (1000.0, 200.0);
+    let mut uniform: Option<(usize, usize)> = None;
+    let mut strategies = Vec::new();
+
+    for arg in env::args().skip(1) {
+        let Some((key, value)) = arg.split_once('=') else {
+            eprintln!("Ignoring argument {}. Expected key=value", arg);
+            continue;
+        };
+        let parsed = match key {
+            "lobbies" => value.parse().map(|v| params.lobby_count = v).is_ok(),
+            "players" => value.parse().map(|v| params.player_count = v).is_ok(),
+            "rate" => value.parse().map(|v| params.arrival_rate = v).is_ok(),
+            "mean" => value.parse().map(|v| mean = v).is_ok(),
+            "std_dev" => value.parse().map(|v| std_dev = v).is_ok(),
+            "uniform" => value.split_once(',').and_then(|(min, max)| Some((min.parse().ok()?, max.parse().ok()?))).map(|v| uniform = Some(v)).is_some(),
+            "tick_ms" => value.parse().ok().filter(|v| *v > 0).map(|v| params.tick = Duration::from_millis(v)).is_some(), // A zero tick never advances the clock
+            "max_wait" => value.parse().map(|v| params.max_wait = Duration::from_secs(v)).is_ok(),
+            "seed" => value.parse().map(|v| params.seed = v).is_ok(),
+            "mode" => match value {
+                "casual" => { params.mode = GameMode::Casual; true },
+                "competitive" => { params.mode = GameMode::Competitive; true },
+                _ => false
+            },
+            "strategy" => parse_strategy(value).map(|strategy| strategies.push(strategy)).is_some(),
+            _ => false
+        };
+        if !parsed {
+            eprintln!("Ignoring invalid argument {}", arg);
+        }
+    }
+
+    params.rating_distribution = match uniform {
+        Some((min, max)) => RatingDistribution::Uniform { min, max },
+        None => RatingDistribution::Normal { mean, std_dev }
+    };
+    if strategies.is_empty() {
+        strategies.push(params.strategy);
+    }
+
+    for strategy in strategies {
+        params.strategy = strategy;
+        println!("{:?}", strategy);
+        println!("{}\n", run_simulation(&params));
+    }
+}
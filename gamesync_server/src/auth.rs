@@ -0,0 +1,346 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use argon2::Argon2;
+use dashmap::DashMap;
+use dashmap::mapref::entry::Entry;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use password_hash::rand_core::OsRng;
+use sha2::Sha256;
+use uuid::Uuid;
+use message_io::network::Endpoint;
+use crate::error::GameSyncError;
+use crate::lobby::{*};
+use crate::networking::{ClientEvent, ServerEvent, Websocket};
+
+// Maps the credentials a client logs in with to the persistent player ID of its account
+pub trait AuthProvider: fmt::Debug + Send + Sync {
+    fn authenticate(&self, credentials: &Credentials) -> Option<PlayerID>; // None rejects the login
+}
+
+// Identity of a connection accepted by an Authenticator
+#[derive(Debug, Clone)]
+pub struct Identity {
+    pub player_id: PlayerID,
+    pub claims: HashMap<String, String>, // e.g. roles or entitlements. Sent back to the client and readable with GameServer::get_claims
+}
+
+// Checks the token a new connection sends in its first event. Err rejects the connection with a reason
+pub trait Authenticator: fmt::Debug + Send + Sync {
+    fn authenticate(&self, token: &str) -> Result<Identity, String>;
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len()).step_by(2).map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok()).collect()
+}
+
+// Passwords are only kept as an Argon2id hash in PHC string format, which carries its own random salt and parameters
+fn hash_password(password: &str) -> String {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default().hash_password(password.as_bytes(), &salt).expect("Argon2 accepts passwords of any length").to_string()
+}
+
+// Constant time comparison. Hashes that are not valid PHC strings never match
+fn verify_password(password: &str, password_hash: &str) -> bool {
+    PasswordHash::new(password_hash).is_ok_and(|hash| Argon2::default().verify_password(password.as_bytes(), &hash).is_ok())
+}
+
+#[derive(Serialize, Deserialize)]
+struct TokenPayload {
+    player_id: PlayerID,
+    expires: u64, // Seconds since the Unix epoch
+    claims: HashMap<String, String>,
+}
+
+// Validates tokens signed with HMAC-SHA256 and a shared secret, without contacting any other service.
+// A token is the hex encoded JSON payload and the hex encoded signature of that payload, joined by a '.'
+#[derive(Debug)]
+pub struct HmacAuthenticator {
+    secret: Vec<u8>,
+}
+
+impl HmacAuthenticator {
+    pub fn new(secret: &[u8]) -> Self {
+        HmacAuthenticator { secret: secret.to_vec() }
+    }
+
+    fn mac(&self) -> Hmac<Sha256> {
+        Hmac::<Sha256>::new_from_slice(&self.secret).expect("HMAC accepts keys of any length")
+    }
+
+    // Signs a token for player_id that is valid for valid_for, e.g. from a login service or a test
+    pub fn issue(&self, player_id: PlayerID, claims: HashMap<String, String>, valid_for: Duration) -> String {
+        let expires = (SystemTime::now() + valid_for).duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        let payload = to_hex(&serde_json::to_vec(&TokenPayload { player_id, expires, claims }).unwrap_or_default());
+        let mut mac = self.mac();
+        mac.update(payload.as_bytes());
+        format!("{}.{}", payload, to_hex(&mac.finalize().into_bytes()))
+    }
+}
+
+impl Authenticator for HmacAuthenticator {
+    fn authenticate(&self, token: &str) -> Result<Identity, String> {
+        let (payload, signature) = token.split_once('.').ok_or("Malformed token")?;
+        let mut mac = self.mac();
+        mac.update(payload.as_bytes());
+        mac.verify_slice(&from_hex(signature).ok_or("Malformed token")?).map_err(|_| "Invalid token signature")?;
+
+        let payload: TokenPayload = from_hex(payload)
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            .ok_or("Malformed token")?;
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        if payload.expires <= now {
+            return Err("Token expired".to_string())
+        }
+        Ok(Identity { player_id: payload.player_id, claims: payload.claims })
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct MemoryAuthProvider {
+    accounts: DashMap<String, (String, PlayerID)>, // Username -> Password hash, Player ID
+    tokens: DashMap<String, PlayerID>,
+}
+
+impl MemoryAuthProvider {
+    pub fn new() -> Self {
+        MemoryAuthProvider::default()
+    }
+
+    // Creates an account and returns its player ID. None if the username is taken or contains whitespace
+    pub fn add_account(&self, username: &str, password: &str) -> Option<PlayerID> {
+        self.insert_account(username, hash_password(password), Uuid::new_v4())
+    }
+
+    pub fn add_token(&self, token: &str, player_id: PlayerID) -> bool {
+        if token.is_empty() || token.contains(char::is_whitespace) {
+            return false;
+        }
+        self.tokens.insert(token.to_string(), player_id);
+        true
+    }
+
+    fn insert_account(&self, username: &str, password_hash: String, player_id: PlayerID) -> Option<PlayerID> {
+        if username.is_empty() || username.contains(char::is_whitespace) {
+            return None;
+        }
+        match self.accounts.entry(username.to_string()) {
+            Entry::Occupied(_) => None,
+            Entry::Vacant(entry) => {
+                entry.insert((password_hash, player_id));
+                Some(player_id)
+            }
+        }
+    }
+}
+
+impl AuthProvider for MemoryAuthProvider {
+    fn authenticate(&self, credentials: &Credentials) -> Option<PlayerID> {
+        match credentials {
+            Credentials::Token(token) => self.tokens.get(token).map(|player_id| *player_id),
+            Credentials::Password { username, password } => {
+                let (password_hash, player_id) = self.accounts.get(username)?.value().clone(); // Not held while hashing
+                verify_password(password, &password_hash).then_some(player_id)
+            }
+        }
+    }
+}
+
+// Same as MemoryAuthProvider, with accounts and tokens appended to a file and loaded back on start.
+// Each line is either "account <username> <password hash> <player id>" or "token <token> <player id>"
+#[derive(Debug)]
+pub struct FileAuthProvider {
+    path: PathBuf,
+    memory: MemoryAuthProvider,
+    file_lock: Mutex<()>, // Serializes appends
+}
+
+impl FileAuthProvider {
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self, GameSyncError> {
+        let path = path.into();
+        let memory = MemoryAuthProvider::new();
+        if path.exists() {
+            for line in BufReader::new(File::open(&path)?).lines() {
+                let line = line?;
+                match line.split_whitespace().collect::<Vec<&str>>().as_slice() {
+                    ["account", username, password_hash, player_id] => {
+                        memory.insert_account(username, password_hash.to_string(), Uuid::parse_str(player_id)?);
+                    },
+                    ["token", token, player_id] => {
+                        memory.add_token(token, Uuid::parse_str(player_id)?);
+                    },
+                    _ => println!("Skipping malformed line in {:?}: {}", path, line)
+                }
+            }
+        }
+        Ok(FileAuthProvider { path, memory, file_lock: Mutex::new(()) })
+    }
+
+    pub fn add_account(&self, username: &str, password: &str) -> Result<Option<PlayerID>, GameSyncError> {
+        let password_hash = hash_password(password);
+        let Some(player_id) = self.memory.insert_account(username, password_hash.clone(), Uuid::new_v4()) else {
+            return Ok(None)
+        };
+        self.append(format!("account {} {} {}", username, password_hash, player_id))?;
+        Ok(Some(player_id))
+    }
+
+    pub fn add_token(&self, token: &str, player_id: PlayerID) -> Result<bool, GameSyncError> {
+        if !self.memory.add_token(token, player_id) {
+            return Ok(false)
+        }
+        self.append(format!("token {} {}", token, player_id))?;
+        Ok(true)
+    }
+
+    fn append(&self, line: String) -> Result<(), GameSyncError> {
+        let _guard = self.file_lock.lock().unwrap();
+        let mut file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        writeln!(file, "{}", line)?;
+        Ok(())
+    }
+}
+
+impl AuthProvider for FileAuthProvider {
+    fn authenticate(&self, credentials: &Credentials) -> Option<PlayerID> {
+        self.memory.authenticate(credentials)
+    }
+}
+
+impl Websocket {
+    pub fn login(&mut self, endpoint: Endpoint, player_id: PlayerID, credentials: Credentials) -> Result<(), GameSyncError> {
+        let Some(provider) = self.data_store.auth_provider() else {
+            return Err(GameSyncError::AuthError)
+        };
+        let Some(account_id) = provider.authenticate(&credentials) else {
+            return Err(GameSyncError::AuthError)
+        };
+        self.bind_account(endpoint, player_id, account_id)
+    }
+
+    // Moves the connection from its guest player ID to the account's persistent one.
+    // The account's player is restored as it was when it last disconnected
+    pub(crate) fn bind_account(&mut self, endpoint: Endpoint, player_id: PlayerID, account_id: PlayerID) -> Result<(), GameSyncError> {
+        if player_id != account_id {
+            if self.find_player(player_id)?.1.is_some() || self.data_store.get_user_endpoint(&account_id).is_some() {
+                return Err(GameSyncError::LoginError)
+            }
+            self.data_store.delete_player(player_id);
+            self.data_store.rebind_user_endpoint(endpoint, account_id);
+            self.restore_account_player(account_id);
+        }
+        self.send_to_client(&account_id.to_string(), ServerEvent::LoggedIn(account_id))?;
+        self.deliver_offline_messages(account_id)?;
+        Ok(())
+    }
+
+    fn restore_account_player(&mut self, account_id: PlayerID) {
+        let player = self.data_store.get_account_player(account_id).unwrap_or(Player {
+            player_id: account_id,
+            rating: self.data_store.initial_rating(),
+            profile: PlayerProfile::default(),
+            role_preferences: Vec::new(),
+        });
+        self.data_store.set_account_player(account_id, player.clone());
+        self.data_store.add_player(account_id, player);
+    }
+
+    // Connections are held here until their Authenticate event is accepted. Pings are answered so clients can pick a
+    // region before authenticating. Anything else is refused
+    pub(crate) fn handle_unauthenticated(&mut self, endpoint: Endpoint, event: ClientEvent) -> Result<(), GameSyncError> {
+        if let ClientEvent::Ping(ping_id) = event {
+            let payload = serde_json::to_string(&ServerEvent::Pong(ping_id, self.data_store.served_regions()))?;
+            self.handler.network().send(endpoint, payload.as_ref());
+            return Ok(())
+        }
+        let (ClientEvent::Authenticate { token }, Some(authenticator)) = (event, self.data_store.authenticator()) else {
+            return Err(GameSyncError::AuthRequiredError)
+        };
+        let identity = authenticator.authenticate(&token).and_then(|identity| {
+            match self.data_store.get_user_endpoint(&identity.player_id) {
+                Some(_) => Err("Player is already connected".to_string()),
+                None => Ok(identity)
+            }
+        });
+        self.data_store.remove_pending_endpoint(endpoint);
+
+        let identity = match identity {
+            Ok(identity) => identity,
+            Err(reason) => {
+                println!("Rejected connection {}: {}", endpoint, reason);
+                let payload = serde_json::to_string(&ServerEvent::AuthRejected(reason))?;
+                self.handler.network().send(endpoint, payload.as_ref());
+                self.handler.network().remove(endpoint.resource_id());
+                return Ok(())
+            }
+        };
+        let player_id = identity.player_id;
+        println!("Connection from {} authenticated", player_id);
+        self.data_store.rebind_user_endpoint(endpoint, player_id);
+        self.data_store.set_claims(player_id, identity.claims.clone());
+        self.restore_account_player(player_id);
+        self.send_to_client(&player_id.to_string(), ServerEvent::Authenticated(player_id, identity.claims))?;
+        self.send_to_all_clients(endpoint, ServerEvent::NewPlayer(player_id.to_string()))?;
+        self.deliver_offline_messages(player_id)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn claims() -> HashMap<String, String> {
+        HashMap::from([("role".to_string(), "moderator".to_string())])
+    }
+
+    #[test]
+    fn token_round_trip() {
+        let authenticator = HmacAuthenticator::new(b"secret");
+        let player_id = Uuid::new_v4();
+        let token = authenticator.issue(player_id, claims(), Duration::from_secs(60));
+        let identity = authenticator.authenticate(&token).unwrap();
+        assert_eq!(identity.player_id, player_id);
+        assert_eq!(identity.claims, claims());
+    }
+
+    #[test]
+    fn expired_token_is_rejected() {
+        let authenticator = HmacAuthenticator::new(b"secret");
+        let token = authenticator.issue(Uuid::new_v4(), claims(), Duration::ZERO);
+        assert_eq!(authenticator.authenticate(&token).unwrap_err(), "Token expired");
+    }
+
+    #[test]
+    fn tampered_token_is_rejected() {
+        let authenticator = HmacAuthenticator::new(b"secret");
+        let token = authenticator.issue(Uuid::new_v4(), claims(), Duration::from_secs(60));
+        let (payload, signature) = token.split_once('.').unwrap();
+
+        // Grant a different player the same signature
+        let forged = TokenPayload { player_id: Uuid::new_v4(), expires: u64::MAX, claims: claims() };
+        let forged = to_hex(&serde_json::to_vec(&forged).unwrap());
+        assert_eq!(authenticator.authenticate(&format!("{}.{}", forged, signature)).unwrap_err(), "Invalid token signature");
+
+        let flipped = if signature.starts_with('0') { "1" } else { "0" };
+        let tampered = format!("{}.{}{}", payload, flipped, &signature[1..]);
+        assert_eq!(authenticator.authenticate(&tampered).unwrap_err(), "Invalid token signature");
+
+        let other = HmacAuthenticator::new(b"other secret");
+        assert_eq!(other.authenticate(&token).unwrap_err(), "Invalid token signature");
+        assert_eq!(authenticator.authenticate("not a token").unwrap_err(), "Malformed token");
+    }
+}
@@ -0,0 +1,105 @@
+use crate::error::{GameSyncError, print_error};
+use crate::lobby::{*};
+use crate::networking::{ServerEvent, Websocket};
+
+// Lobbies that lose a player during a match can be topped up by solo players waiting in the party queue.
+// Teams assembled from partial lobbies are broken up when a player leaves, so only regular lobbies are backfilled
+impl Websocket {
+    // Opens a backfill request for an in-game lobby below lobby size and fills it from the queued solo players
+    pub(crate) fn open_backfill(&mut self, lobby: &Lobby) -> Result<(), GameSyncError> {
+        if !self.data_store.backfill() || lobby.status != LobbyStatus::Ingame || !lobby.parties.is_empty() {
+            return Ok(())
+        }
+        if self.data_store.get_lobby_match(lobby.lobby_id).is_none_or(|game| game.deadline.is_some()) { // No match in progress
+            return Ok(())
+        }
+        let (region, mode) = (lobby.params.region.clone(), lobby.params.mode);
+        println!("Backfill opened for lobby {}", lobby.lobby_id);
+        self.data_store.add_backfill_lobby(region.clone(), mode, lobby.lobby_id);
+        for party_id in self.data_store.get_party_lobbies(region.clone(), mode) {
+            if self.data_store.get_backfill_lobbies(region.clone(), mode).is_empty() {
+                break;
+            }
+            self.fill_backfill(region.clone(), party_id)?;
+        }
+        Ok(())
+    }
+
+    // Moves a queued solo player into the oldest open backfill they fit in. Hard tags must agree, and competitive players
+    // must be within both lobbies' thresholds of the in-game lobby's average rating. Returns whether the player was placed
+    pub(crate) fn fill_backfill(&mut self, region: Region, lobby_id: LobbyID) -> Result<bool, GameSyncError> {
+        let solo = match self.data_store.get_lobby(region.clone(), lobby_id) {
+            Some(lobby) if lobby.player_list.len() == 1 && lobby.status == LobbyStatus::Queueing && self.data_store.get_party_team(lobby_id).is_none() => lobby,
+            _ => return Ok(false)
+        };
+        let mode = solo.params.mode;
+        let player_id = solo.player_list[0];
+        let solo_rating = self.data_store.get_lobby_average_rating(region.clone(), lobby_id);
+
+        for target_id in self.data_store.get_backfill_lobbies(region.clone(), mode) {
+            let target = self.data_store.get_lobby(region.clone(), target_id)
+                .filter(|target| target.status == LobbyStatus::Ingame && target.player_list.len() < self.data_store.lobby_size());
+            let game = self.data_store.get_lobby_match(target_id).filter(|game| game.deadline.is_none());
+            let (target, game) = match (target, game) {
+                (Some(target), Some(game)) => (target, game),
+                _ => { // Lobby was refilled, left the game or the match is over
+                    self.data_store.remove_backfill_lobby(region.clone(), mode, target_id);
+                    continue;
+                }
+            };
+            if !target.params.hard_tags_agree(&solo.params) {
+                continue;
+            }
+            if mode == GameMode::Competitive {
+                let gap = solo_rating.abs_diff(self.data_store.get_lobby_average_rating(region.clone(), target_id));
+                if gap > target.queue_threshold || gap > solo.queue_threshold {
+                    continue;
+                }
+            }
+            let players: Vec<PlayerID> = target.player_list.iter().copied().chain([player_id]).collect();
+            if self.get_role_assignment(&players).is_none() {
+                continue;
+            }
+            self.join_backfill(target, game, solo)?;
+            return Ok(true)
+        }
+        Ok(false)
+    }
+
+    fn join_backfill(&mut self, mut target: Lobby, mut game: Match, solo: Lobby) -> Result<(), GameSyncError> {
+        let region = target.params.region.clone();
+        let player_id = solo.leader;
+        self.data_store.remove_party_lobby(region.clone(), solo.params.mode, solo.lobby_id);
+        self.data_store.delete_lobby(region.clone(), solo.lobby_id)?;
+        self.data_store.delete_region_lobby(solo.lobby_id)?;
+
+        target.player_list.push(player_id);
+        self.data_store.edit_lobby(region.clone(), target.lobby_id, target.clone())?;
+        self.data_store.edit_player(player_id, None, Some(target.lobby_id));
+        if target.player_list.len() >= self.data_store.lobby_size() {
+            self.data_store.remove_backfill_lobby(region.clone(), target.params.mode, target.lobby_id);
+        }
+
+        // The new player is rated and can be penalized with the rest of the match
+        let opponent = if game.lobbies.0.lobby_id == target.lobby_id {
+            game.lobbies.0.player_list.push(player_id);
+            game.lobbies.1.clone()
+        } else {
+            game.lobbies.1.player_list.push(player_id);
+            game.lobbies.0.clone()
+        };
+        self.data_store.edit_match(game.match_id, game.clone());
+        let opponent = self.data_store.get_lobby(opponent.params.region.clone(), opponent.lobby_id).unwrap_or(opponent);
+        println!("Player {} backfilled into lobby {} for match {}", player_id, target.lobby_id, game.match_id);
+
+        for player_id_match in target.player_list.iter().chain(opponent.player_list.iter()) {
+            if let Err(e) = self.send_to_client(&player_id_match.to_string(), ServerEvent::BackfillJoined(game.match_id, target.lobby_id, player_id)) {
+                print_error(e);
+            }
+        }
+        let role = self.get_role_assignment(&target.player_list).and_then(|roles| roles.get(&player_id).cloned());
+        self.send_to_client(&player_id.to_string(), ServerEvent::LobbyInfo(target.clone(), self.lobby_roster(&target)))?;
+        self.send_to_client(&player_id.to_string(), ServerEvent::MatchFound(MatchInfo { match_id: game.match_id, region: game.region.clone(), opponent, role }))?;
+        Ok(())
+    }
+}
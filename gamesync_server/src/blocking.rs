@@ -0,0 +1,47 @@
+use message_io::network::Endpoint;
+use crate::error::{GameSyncError, print_error};
+use crate::lobby::{*};
+use crate::filters::MessageKind;
+use crate::networking::{ServerEvent, Websocket};
+
+// Messages, invites and friend requests from blocked players are dropped without telling the sender
+impl Websocket {
+    pub fn block_player(&mut self, player_id: PlayerID, target_id: PlayerID) -> Result<(), GameSyncError> {
+        if player_id == target_id || !self.data_store.block_player(player_id, target_id) {
+            return Err(GameSyncError::BlockError)
+        }
+        // Blocking ends the friendship and any pending request between the two players
+        self.data_store.remove_friend_request(player_id, target_id);
+        self.data_store.remove_friend_request(target_id, player_id);
+        if self.data_store.remove_friends(player_id, target_id) {
+            self.send_to_client(&player_id.to_string(), ServerEvent::FriendRemoved(target_id))?;
+        }
+        self.send_to_client(&player_id.to_string(), ServerEvent::PlayerBlocked(target_id))?;
+        Ok(())
+    }
+
+    pub fn unblock_player(&mut self, player_id: PlayerID, target_id: PlayerID) -> Result<(), GameSyncError> {
+        if !self.data_store.unblock_player(player_id, target_id) {
+            return Err(GameSyncError::UnblockError)
+        }
+        self.send_to_client(&player_id.to_string(), ServerEvent::PlayerUnblocked(target_id))?;
+        Ok(())
+    }
+
+    pub fn broadcast(&mut self, player_id: PlayerID, msg_sender: Endpoint, message: String) -> Result<(), GameSyncError> {
+        let message = self.filter_message(player_id, MessageKind::Broadcast, message)?;
+        let summary = self.player_summary(player_id);
+        for endpoint in self.data_store.get_all_user_endpoints() {
+            let Some(receiver_id) = self.data_store.get_user(endpoint) else {
+                continue;
+            };
+            if endpoint == msg_sender || self.data_store.is_blocked(receiver_id, player_id) {
+                continue;
+            }
+            if let Err(e) = self.send_to_client(&receiver_id.to_string(), ServerEvent::UserMessage(summary.clone(), message.clone())) {
+                print_error(e);
+            }
+        }
+        Ok(())
+    }
+}
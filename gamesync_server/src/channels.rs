@@ -0,0 +1,149 @@
+use crate::error::{GameSyncError, print_error};
+use crate::lobby::{*};
+use crate::filters::MessageKind;
+use crate::networking::{ServerEvent, Websocket};
+
+const MAX_CHANNEL_NAME_LENGTH: usize = 32; // In characters. Only enforced for channels created by players
+
+impl Channel {
+    pub fn global(name: &str) -> Self {
+        Channel { name: name.to_string(), kind: ChannelKind::Global, post: PostPermission::Members, owner: None, moderators: Vec::new() }
+    }
+}
+
+impl Websocket {
+    fn can_join_channel(&self, player_id: PlayerID, channel: &Channel) -> bool {
+        match &channel.kind {
+            ChannelKind::Guild(guild) => {
+                self.data_store.get_claims(player_id).is_some_and(|claims| claims.get("guild") == Some(guild))
+            },
+            ChannelKind::Regional(region) => { // Only players in a lobby of that region
+                self.data_store.get_player_info(player_id)
+                    .and_then(|(_, lobby_id)| lobby_id)
+                    .and_then(|lobby_id| self.data_store.get_region_lobby(&lobby_id))
+                    .is_some_and(|lobby_region| lobby_region == *region)
+            },
+            _ => true
+        }
+    }
+
+    fn find_channel(&self, name: &str) -> Result<Channel, GameSyncError> {
+        self.data_store.get_channel(name).ok_or(GameSyncError::ChannelFindError)
+    }
+
+    // The creator owns and moderates the channel
+    pub fn create_channel(&mut self, player_id: PlayerID, name: String, post: PostPermission) -> Result<(), GameSyncError> {
+        let name_length = name.chars().count();
+        if name_length == 0 || name_length > MAX_CHANNEL_NAME_LENGTH || name.trim() != name || name.chars().any(char::is_control) {
+            return Err(GameSyncError::ChannelCreateError)
+        }
+        let channel = Channel { name: name.clone(), kind: ChannelKind::Custom, post, owner: Some(player_id), moderators: vec![player_id] };
+        if !self.data_store.add_channel(channel.clone()) {
+            return Err(GameSyncError::ChannelCreateError)
+        }
+        self.data_store.add_channel_member(&name, player_id);
+        self.send_to_client(&player_id.to_string(), ServerEvent::ChannelJoined(channel))?;
+        Ok(())
+    }
+
+    pub fn join_channel(&mut self, player_id: PlayerID, name: String) -> Result<(), GameSyncError> {
+        let channel = self.find_channel(&name)?;
+        if !self.can_join_channel(player_id, &channel) || !self.data_store.add_channel_member(&name, player_id) {
+            return Err(GameSyncError::ChannelJoinError)
+        }
+        self.send_to_client(&player_id.to_string(), ServerEvent::ChannelJoined(channel))?;
+        Ok(())
+    }
+
+    pub fn leave_channel(&mut self, player_id: PlayerID, name: String) -> Result<(), GameSyncError> {
+        if !self.data_store.remove_channel_member(&name, player_id) {
+            return Err(GameSyncError::ChannelMemberError)
+        }
+        self.send_to_client(&player_id.to_string(), ServerEvent::ChannelLeft(name))?;
+        Ok(())
+    }
+
+    pub fn message_channel(&mut self, player_id: PlayerID, name: String, message: String) -> Result<(), GameSyncError> {
+        let channel = self.find_channel(&name)?;
+        if !self.data_store.is_channel_member(&name, player_id) {
+            return Err(GameSyncError::ChannelMemberError)
+        }
+        if channel.post == PostPermission::Moderators && !channel.moderators.contains(&player_id) {
+            return Err(GameSyncError::ChannelPostError)
+        }
+        let message = self.filter_message(player_id, MessageKind::Channel(name.clone()), message)?;
+        let summary = self.player_summary(player_id);
+        for member_id in self.data_store.get_channel_members(&name) {
+            if self.data_store.is_blocked(member_id, player_id) {
+                continue;
+            }
+            let event = ServerEvent::ChannelMessage(name.clone(), summary.clone(), message.clone());
+            if let Err(e) = self.send_to_client(&member_id.to_string(), event) {
+                print_error(e);
+            }
+        }
+        Ok(())
+    }
+
+    // Moderators can kick anyone but the owner
+    pub fn kick_from_channel(&mut self, player_id: PlayerID, name: String, target_id: PlayerID) -> Result<(), GameSyncError> {
+        let channel = self.find_channel(&name)?;
+        if !channel.moderators.contains(&player_id) || channel.owner == Some(target_id) {
+            return Err(GameSyncError::ChannelModerateError)
+        }
+        if !self.data_store.remove_channel_member(&name, target_id) {
+            return Err(GameSyncError::ChannelMemberError)
+        }
+        self.send_to_client(&target_id.to_string(), ServerEvent::ChannelLeft(name))?;
+        Ok(())
+    }
+
+    pub fn set_channel_moderator(&mut self, player_id: PlayerID, name: String, target_id: PlayerID, moderator: bool) -> Result<(), GameSyncError> {
+        let mut channel = self.find_channel(&name)?;
+        if channel.owner != Some(player_id) || target_id == player_id {
+            return Err(GameSyncError::ChannelModerateError)
+        }
+        if !self.data_store.is_channel_member(&name, target_id) {
+            return Err(GameSyncError::ChannelMemberError)
+        }
+        channel.moderators.retain(|moderator_id| *moderator_id != target_id);
+        if moderator {
+            channel.moderators.push(target_id);
+        }
+        self.data_store.set_channel(channel.clone());
+        for member_id in self.data_store.get_channel_members(&name) {
+            if let Err(e) = self.send_to_client(&member_id.to_string(), ServerEvent::ChannelUpdated(channel.clone())) {
+                print_error(e);
+            }
+        }
+        Ok(())
+    }
+
+    // Every channel the player is allowed to join, including the ones they are in
+    pub fn get_channels(&mut self, player_id: PlayerID) -> Result<(), GameSyncError> {
+        let channels = self.data_store.get_channels().into_iter()
+            .filter(|channel| self.can_join_channel(player_id, channel))
+            .collect();
+        self.send_to_client(&player_id.to_string(), ServerEvent::Channels(channels))?;
+        Ok(())
+    }
+
+    pub fn add_server_channel(&mut self, channel: Channel) -> Result<(), GameSyncError> {
+        if !self.data_store.add_channel(channel) {
+            return Err(GameSyncError::ChannelCreateError)
+        }
+        Ok(())
+    }
+
+    pub fn remove_channel(&mut self, name: &str) -> Result<(), GameSyncError> {
+        let Some(members) = self.data_store.remove_channel(name) else {
+            return Err(GameSyncError::ChannelFindError)
+        };
+        for member_id in members {
+            if let Err(e) = self.send_to_client(&member_id.to_string(), ServerEvent::ChannelLeft(name.to_string())) {
+                print_error(e);
+            }
+        }
+        Ok(())
+    }
+}
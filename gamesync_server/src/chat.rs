@@ -0,0 +1,79 @@
+use std::sync::Arc;
+use std::time::Duration;
+use crate::error::GameSyncError;
+use crate::filters::{MaxLengthFilter, MessageFilter, MessageKind};
+use crate::lobby::{*};
+use crate::networking::{ServerEvent, Websocket};
+
+#[derive(Debug, Clone)]
+pub struct ChatParams {
+    pub conversation_history: usize, // Direct messages kept per pair of players. The oldest are dropped first
+    pub offline_messages: usize, // Direct messages queued for an offline account until it reconnects
+    pub max_page_size: usize, // Most messages returned by a single GetConversation
+    pub lobby_history: usize, // Lobby messages kept per lobby and sent to players who join it
+    pub channels: Vec<Channel>, // Server-managed channels available from the start. Players can create more
+    pub filters: Vec<Arc<dyn MessageFilter>>, // Run in order on every direct, broadcast, lobby and channel message
+    pub report_context: usize, // Recent messages kept per player and attached to reports against them
+    pub max_mute: Duration, // Longer mutes requested by lobby leaders are shortened to this
+}
+
+impl Default for ChatParams {
+    fn default() -> Self {
+        ChatParams {
+            conversation_history: 100,
+            offline_messages: 100,
+            max_page_size: 50,
+            lobby_history: 50,
+            channels: vec![Channel::global("global")],
+            filters: vec![Arc::new(MaxLengthFilter::new(500))],
+            report_context: 20,
+            max_mute: Duration::from_secs(24 * 60 * 60),
+        }
+    }
+}
+
+impl Websocket {
+    // Offline accounts get the message when they next log in. Guests can not be reached once they disconnect
+    pub fn send_direct_message(&mut self, player_id: PlayerID, to: PlayerID, message: String) -> Result<(), GameSyncError> {
+        if self.data_store.is_blocked(to, player_id) { // Dropped without telling the sender
+            return Ok(())
+        }
+        let online = self.data_store.get_user_endpoint(&to).is_some();
+        if !online && self.data_store.get_account_player(to).is_none() {
+            return Err(GameSyncError::SendError)
+        }
+        let message = self.filter_message(player_id, MessageKind::Direct(to), message)?;
+        let direct_message = self.data_store.add_direct_message(player_id, to, message.clone());
+        if online {
+            self.send_to_client(&to.to_string(), ServerEvent::UserMessage(self.player_summary(player_id), message))?;
+        } else {
+            self.data_store.queue_offline_message(direct_message);
+        }
+        Ok(())
+    }
+
+    // Page of the conversation with another player, oldest message first. before pages back from a message ID
+    pub fn get_conversation(&mut self, player_id: PlayerID, with: PlayerID, before: Option<u64>, limit: usize) -> Result<(), GameSyncError> {
+        let limit = limit.min(self.data_store.chat_params().max_page_size);
+        let messages = self.data_store.get_conversation(player_id, with, before, limit);
+        self.send_to_client(&player_id.to_string(), ServerEvent::Conversation(with, messages))?;
+        Ok(())
+    }
+
+    pub fn get_lobby_chat_history(&mut self, player_id: PlayerID, lobby_id: LobbyID) -> Result<(), GameSyncError> {
+        if self.find_player(player_id)?.1 != Some(lobby_id) {
+            return Err(GameSyncError::LobbyChatError)
+        }
+        let messages = self.data_store.get_lobby_chat(lobby_id);
+        self.send_to_client(&player_id.to_string(), ServerEvent::LobbyChatHistory(lobby_id, messages))?;
+        Ok(())
+    }
+
+    pub(crate) fn deliver_offline_messages(&mut self, player_id: PlayerID) -> Result<(), GameSyncError> {
+        let messages = self.data_store.take_offline_messages(player_id);
+        if !messages.is_empty() {
+            self.send_to_client(&player_id.to_string(), ServerEvent::OfflineMessages(messages))?;
+        }
+        Ok(())
+    }
+}
@@ -2,6 +2,7 @@ use std::fmt;
 use serde_json::Error as ParseError;
 use std::io::Error as SocketError;
 use uuid::Error as UuidError;
+use std::time::SystemTime;
 
 #[derive(Debug)]
 pub enum GameSyncError {
@@ -28,6 +29,38 @@ pub enum GameSyncError {
     LobbyDeleteError,
     LobbyStopError,
     LeaveGameError,
+    MatchFindError,
+    MatchReportError,
+    MatchAcceptError,
+    QueueBanned { until: SystemTime },
+    QueueStatusError,
+    RegionError,
+    TeamMemberError,
+    LeaderboardModeError,
+    RoleError,
+    RoleCompositionError,
+    FriendRequestError,
+    FriendFindError,
+    BlockError,
+    UnblockError,
+    LobbyBlockedError,
+    AuthError,
+    LoginError,
+    AuthenticatedError,
+    AuthRequiredError,
+    ProfileInvalidError,
+    ProfileNameTakenError,
+    LobbyChatError,
+    ChannelCreateError,
+    ChannelFindError,
+    ChannelJoinError,
+    ChannelMemberError,
+    ChannelPostError,
+    ChannelModerateError,
+    MessageRejected { reason: String },
+    LobbyMuteError,
+    LobbyMutedError { until: SystemTime },
+    ReportError,
 }
 
 impl From<ParseError> for GameSyncError {
@@ -74,6 +107,38 @@ impl fmt::Display for GameSyncError {
             GameSyncError::LobbyDeleteError => write!(f, "Failed to delete lobby. Lobby is not idle"),
             GameSyncError::LobbyStopError => write!(f, "Failed to stop queue. Lobby is not currently in queue"),
             GameSyncError::LeaveGameError => write!(f, "Failed to leave game. Lobby is not currently in-game"),
+            GameSyncError::MatchFindError => write!(f, "Match not found."),
+            GameSyncError::MatchReportError => write!(f, "Failed to report match result. Match not in progress or player not a lobby leader in this match"),
+            GameSyncError::MatchAcceptError => write!(f, "Failed to respond to match. Player not part of a match awaiting ready check"),
+            GameSyncError::QueueBanned { until } => write!(f, "Failed to queue. A player in the lobby is banned from queueing until {:?}", until),
+            GameSyncError::QueueStatusError => write!(f, "Failed to get queue status. Player not part of lobby or lobby is not in queue"),
+            GameSyncError::RegionError => write!(f, "Region is not served by this server"),
+            GameSyncError::TeamMemberError => write!(f, "Player is not in any party of this team"),
+            GameSyncError::LeaderboardModeError => write!(f, "Leaderboards only rank competitive players"),
+            GameSyncError::RoleError => write!(f, "Failed to set role preferences. Unknown role or lobby is not idle"),
+            GameSyncError::RoleCompositionError => write!(f, "Failed to queue. Players' role preferences do not fit the role composition"),
+            GameSyncError::FriendRequestError => write!(f, "Failed to send friend request. Already friends or request already sent"),
+            GameSyncError::FriendFindError => write!(f, "Friend or friend request not found"),
+            GameSyncError::BlockError => write!(f, "Failed to block player. Player already blocked or is the sender"),
+            GameSyncError::UnblockError => write!(f, "Failed to unblock player. Player is not blocked"),
+            GameSyncError::LobbyBlockedError => write!(f, "Failed to join lobby. Blocked by the lobby leader"),
+            GameSyncError::AuthError => write!(f, "Failed to log in. Invalid credentials or logins are disabled"),
+            GameSyncError::LoginError => write!(f, "Failed to log in. Player is in a lobby or account is already connected"),
+            GameSyncError::AuthenticatedError => write!(f, "Failed to authenticate. Connection is already authenticated"),
+            GameSyncError::AuthRequiredError => write!(f, "Connection must authenticate before sending other events"),
+            GameSyncError::ProfileInvalidError => write!(f, "Failed to edit profile. Display name or metadata breaks the server's profile rules"),
+            GameSyncError::ProfileNameTakenError => write!(f, "Failed to edit profile. Display name is already taken"),
+            GameSyncError::LobbyChatError => write!(f, "Failed to get lobby chat history. Player not part of lobby"),
+            GameSyncError::ChannelCreateError => write!(f, "Failed to create channel. Name is invalid or already taken"),
+            GameSyncError::ChannelFindError => write!(f, "Channel not found"),
+            GameSyncError::ChannelJoinError => write!(f, "Failed to join channel. Already a member or not allowed to join"),
+            GameSyncError::ChannelMemberError => write!(f, "Player not a member of channel"),
+            GameSyncError::ChannelPostError => write!(f, "Failed to send message. Only moderators can post in this channel"),
+            GameSyncError::ChannelModerateError => write!(f, "Failed to moderate channel. Player lacks permission"),
+            GameSyncError::MessageRejected { reason } => write!(f, "Message rejected by chat filters: {}", reason),
+            GameSyncError::LobbyMuteError => write!(f, "Failed to mute player. Player is the leader, not in the lobby or not muted"),
+            GameSyncError::LobbyMutedError { until } => write!(f, "Failed to send message. Player is muted in this lobby until {:?}", until),
+            GameSyncError::ReportError => write!(f, "Failed to report player. Player already reported, reason too long or player is the sender"),
         }
     }
 }
@@ -0,0 +1,212 @@
+use std::collections::{HashSet, VecDeque};
+use std::fmt;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use dashmap::DashMap;
+use crate::error::GameSyncError;
+use crate::lobby::{*};
+use crate::networking::{ServerEvent, Websocket};
+
+// Where a chat message is being sent
+#[derive(Debug, Clone, PartialEq)]
+pub enum MessageKind {
+    Direct(PlayerID), // Recipient ID
+    Broadcast,
+    Lobby(LobbyID),
+    Channel(String), // Channel name
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FilterAction {
+    Allow,
+    Rewrite(String), // Replaces the text passed to the next filter and sent to the receivers
+    Reject(String), // Reason sent back to the sender. The message is dropped
+}
+
+// Filters run in order before a message is stored or sent. The first rejection stops the chain
+pub trait MessageFilter: fmt::Debug + Send + Sync {
+    fn filter(&self, sender: PlayerID, kind: &MessageKind, message: &str) -> FilterAction;
+    // Called once the whole chain accepted the message, with the text as this filter saw it
+    fn accepted(&self, _sender: PlayerID, _kind: &MessageKind, _message: &str) {}
+}
+
+#[derive(Debug)]
+pub struct MaxLengthFilter {
+    max_length: usize, // In characters
+}
+
+impl MaxLengthFilter {
+    pub fn new(max_length: usize) -> Self {
+        MaxLengthFilter { max_length }
+    }
+}
+
+impl MessageFilter for MaxLengthFilter {
+    fn filter(&self, _sender: PlayerID, _kind: &MessageKind, message: &str) -> FilterAction {
+        if message.chars().count() > self.max_length {
+            return FilterAction::Reject(format!("Message is longer than {} characters", self.max_length))
+        }
+        FilterAction::Allow
+    }
+}
+
+// Rejects a message the sender already sent max_repeats times within the window, wherever it was sent.
+// Only messages that made it through the whole chain count
+#[derive(Debug)]
+pub struct FloodFilter {
+    max_repeats: usize,
+    window: Duration,
+    recent: DashMap<PlayerID, VecDeque<(Instant, String)>>, // Sender -> Messages sent within the window
+    last_sweep: Mutex<Instant>, // Senders with nothing left in the window are dropped at most once per window
+}
+
+impl FloodFilter {
+    pub fn new(max_repeats: usize, window: Duration) -> Self {
+        FloodFilter { max_repeats, window, recent: DashMap::new(), last_sweep: Mutex::new(Instant::now()) }
+    }
+
+    fn prune(&self, recent: &mut VecDeque<(Instant, String)>, now: Instant) {
+        while recent.front().is_some_and(|(sent_at, _)| now.duration_since(*sent_at) > self.window) {
+            recent.pop_front();
+        }
+    }
+}
+
+impl MessageFilter for FloodFilter {
+    fn filter(&self, sender: PlayerID, _kind: &MessageKind, message: &str) -> FilterAction {
+        let normalized = message.trim().to_lowercase();
+        let Some(mut recent) = self.recent.get_mut(&sender) else {
+            return FilterAction::Allow
+        };
+        self.prune(&mut recent, Instant::now());
+        if recent.iter().filter(|(_, sent)| *sent == normalized).count() >= self.max_repeats {
+            return FilterAction::Reject("Message repeated too often".to_string())
+        }
+        FilterAction::Allow
+    }
+
+    fn accepted(&self, sender: PlayerID, _kind: &MessageKind, message: &str) {
+        let now = Instant::now();
+        self.recent.entry(sender).or_default().push_back((now, message.trim().to_lowercase()));
+        let mut last_sweep = self.last_sweep.lock().unwrap();
+        if now.duration_since(*last_sweep) >= self.window {
+            *last_sweep = now;
+            self.recent.retain(|_, recent| {
+                self.prune(recent, now);
+                !recent.is_empty()
+            });
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum WordListAction {
+    Redact, // Listed words are replaced with '*'
+    Reject,
+}
+
+// Matches whole words, ignoring case. Entries that are not a single word could never match and are skipped
+#[derive(Debug)]
+pub struct WordListFilter {
+    words: HashSet<String>, // Lowercase
+    action: WordListAction,
+}
+
+impl WordListFilter {
+    pub fn new<S: AsRef<str>>(words: impl IntoIterator<Item = S>, action: WordListAction) -> Self {
+        let mut list = HashSet::new();
+        for word in words {
+            let word = word.as_ref().to_lowercase();
+            if word.is_empty() || !word.chars().all(char::is_alphanumeric) {
+                println!("Skipping word list entry that is not a single word: {:?}", word);
+                continue;
+            }
+            list.insert(word);
+        }
+        WordListFilter { words: list, action }
+    }
+}
+
+impl MessageFilter for WordListFilter {
+    fn filter(&self, _sender: PlayerID, _kind: &MessageKind, message: &str) -> FilterAction {
+        let mut filtered = String::with_capacity(message.len());
+        let mut word = String::new();
+        let mut found = false;
+        for c in message.chars().chain(std::iter::once(' ')) { // Trailing space ends the last word
+            if c.is_alphanumeric() {
+                word.push(c);
+                continue;
+            }
+            if self.words.contains(&word.to_lowercase()) {
+                found = true;
+                filtered.extend(word.chars().map(|_| '*'));
+            } else {
+                filtered.push_str(&word);
+            }
+            word.clear();
+            filtered.push(c);
+        }
+        filtered.pop();
+        match (found, self.action) {
+            (false, _) => FilterAction::Allow,
+            (true, WordListAction::Redact) => FilterAction::Rewrite(filtered),
+            (true, WordListAction::Reject) => FilterAction::Reject("Message contains a blocked word".to_string()),
+        }
+    }
+}
+
+impl Websocket {
+    // Text to send after the server's filters ran. A rejection is sent to the sender as MessageRejected.
+    // Accepted messages are kept as context for reports
+    pub(crate) fn filter_message(&mut self, player_id: PlayerID, kind: MessageKind, message: String) -> Result<String, GameSyncError> {
+        let filters = self.data_store.message_filters();
+        let mut message = message;
+        let mut seen = Vec::with_capacity(filters.len()); // Text each filter was given
+        for filter in filters.iter() {
+            seen.push(message.clone());
+            match filter.filter(player_id, &kind, &message) {
+                FilterAction::Allow => {},
+                FilterAction::Rewrite(rewritten) => message = rewritten,
+                FilterAction::Reject(reason) => {
+                    self.send_to_client(&player_id.to_string(), ServerEvent::MessageRejected(reason.clone()))?;
+                    return Err(GameSyncError::MessageRejected { reason })
+                }
+            }
+        }
+        for (filter, filtered) in filters.iter().zip(seen.iter()) {
+            filter.accepted(player_id, &kind, filtered);
+        }
+        self.data_store.add_recent_message(player_id, kind, message.clone());
+        Ok(message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter(filter: &WordListFilter, message: &str) -> FilterAction {
+        filter.filter(PlayerID::nil(), &MessageKind::Broadcast, message)
+    }
+
+    #[test]
+    fn word_list_redacts_whole_words_ignoring_case() {
+        let words = WordListFilter::new(["darn"], WordListAction::Redact);
+        assert_eq!(filter(&words, "Darn it, DARN!"), FilterAction::Rewrite("**** it, ****!".to_string()));
+        assert_eq!(filter(&words, "darnation"), FilterAction::Allow);
+        assert_eq!(filter(&words, "hello there"), FilterAction::Allow);
+    }
+
+    #[test]
+    fn word_list_rejects() {
+        let words = WordListFilter::new(["darn"], WordListAction::Reject);
+        assert!(matches!(filter(&words, "oh darn"), FilterAction::Reject(_)));
+    }
+
+    #[test]
+    fn word_list_skips_multi_word_entries() {
+        let words = WordListFilter::new(["two words", "", "ok"], WordListAction::Redact);
+        assert_eq!(filter(&words, "two words"), FilterAction::Allow);
+        assert_eq!(filter(&words, "ok"), FilterAction::Rewrite("**".to_string()));
+    }
+}
@@ -0,0 +1,92 @@
+use crate::error::{GameSyncError, print_error};
+use crate::lobby::{*};
+use crate::networking::{ServerEvent, Websocket};
+
+// Friendships and pending requests are kept by player ID and survive disconnects.
+// Presence is derived from the player's lobby status and pushed to online friends whenever it changes
+impl Websocket {
+    pub fn send_friend_request(&mut self, player_id: PlayerID, target_id: PlayerID) -> Result<(), GameSyncError> {
+        if player_id == target_id || self.data_store.get_friends(player_id).contains(&target_id) {
+            return Err(GameSyncError::FriendRequestError)
+        }
+        self.find_player(target_id)?;
+        if self.data_store.is_blocked(target_id, player_id) { // Dropped without telling the sender
+            return Ok(())
+        }
+
+        // Sending a request to someone who already asked accepts theirs
+        if self.data_store.get_incoming_friend_requests(player_id).contains(&target_id) {
+            return self.accept_friend_request(player_id, target_id)
+        }
+        if !self.data_store.add_friend_request(player_id, target_id) {
+            return Err(GameSyncError::FriendRequestError)
+        }
+        self.send_to_client(&target_id.to_string(), ServerEvent::FriendRequestReceived(player_id))?;
+        Ok(())
+    }
+
+    pub fn accept_friend_request(&mut self, player_id: PlayerID, sender_id: PlayerID) -> Result<(), GameSyncError> {
+        if !self.data_store.remove_friend_request(sender_id, player_id) {
+            return Err(GameSyncError::FriendFindError)
+        }
+        self.data_store.add_friends(player_id, sender_id);
+
+        let sender = FriendInfo { player_id: sender_id, presence: self.data_store.get_presence(sender_id) };
+        let player = FriendInfo { player_id, presence: self.data_store.get_presence(player_id) };
+        self.send_to_client(&player_id.to_string(), ServerEvent::FriendAdded(sender))?;
+        if self.data_store.get_user_endpoint(&sender_id).is_some() { // Sender may be offline
+            self.send_to_client(&sender_id.to_string(), ServerEvent::FriendAdded(player))?;
+        }
+        Ok(())
+    }
+
+    pub fn decline_friend_request(&mut self, player_id: PlayerID, sender_id: PlayerID) -> Result<(), GameSyncError> {
+        if !self.data_store.remove_friend_request(sender_id, player_id) {
+            return Err(GameSyncError::FriendFindError)
+        }
+        Ok(())
+    }
+
+    pub fn remove_friend(&mut self, player_id: PlayerID, friend_id: PlayerID) -> Result<(), GameSyncError> {
+        if !self.data_store.remove_friends(player_id, friend_id) {
+            return Err(GameSyncError::FriendFindError)
+        }
+        self.send_to_client(&player_id.to_string(), ServerEvent::FriendRemoved(friend_id))?;
+        if self.data_store.get_user_endpoint(&friend_id).is_some() {
+            self.send_to_client(&friend_id.to_string(), ServerEvent::FriendRemoved(player_id))?;
+        }
+        Ok(())
+    }
+
+    pub fn get_friends(&mut self, player_id: PlayerID) -> Result<(), GameSyncError> {
+        let friends = self.data_store.get_friends(player_id)
+            .into_iter()
+            .map(|friend_id| FriendInfo { player_id: friend_id, presence: self.data_store.get_presence(friend_id) })
+            .collect();
+        let friend_list = FriendList {
+            friends,
+            incoming_requests: self.data_store.get_incoming_friend_requests(player_id),
+            outgoing_requests: self.data_store.get_outgoing_friend_requests(player_id),
+        };
+        self.send_to_client(&player_id.to_string(), ServerEvent::Friends(friend_list))?;
+        Ok(())
+    }
+
+    // Pushes the presence of every player whose lobby or connection changed since the last call
+    pub(crate) fn flush_presence(&mut self) {
+        for player_id in self.data_store.take_presence_dirty() {
+            let presence = self.data_store.get_presence(player_id);
+            if !self.data_store.set_presence(player_id, presence) {
+                continue;
+            }
+            for friend_id in self.data_store.get_friends(player_id) {
+                if self.data_store.get_user_endpoint(&friend_id).is_none() {
+                    continue;
+                }
+                if let Err(e) = self.send_to_client(&friend_id.to_string(), ServerEvent::FriendPresenceChanged(player_id, presence)) {
+                    print_error(e);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,40 @@
+use crate::error::GameSyncError;
+use crate::lobby::{*};
+use crate::networking::{ServerEvent, Websocket};
+
+const MAX_LEADERBOARD_PAGE: usize = 100; // Most entries returned by a single GetLeaderboard
+
+// Players are added to the leaderboard of a region once they finish a competitive match there. Casual matches do not
+// change ratings, so there is no casual leaderboard. Guests are taken off when they disconnect.
+// Entries are kept ordered as ratings change, so requests never sort. Pages and positions are still found by walking
+// every entry ranked above them, so a request costs O(offset + limit) and a position lookup O(position)
+impl Websocket {
+    pub fn leaderboard_page(&self, region: Region, mode: GameMode, offset: usize, limit: usize) -> Result<Leaderboard, GameSyncError> {
+        if !self.data_store.is_valid_region(&region) {
+            return Err(GameSyncError::RegionError)
+        }
+        if mode != GameMode::Competitive {
+            return Err(GameSyncError::LeaderboardModeError)
+        }
+        let (entries, total_players) = self.data_store.get_leaderboard(region.clone(), mode, offset, limit.min(MAX_LEADERBOARD_PAGE));
+        Ok(Leaderboard { region, mode, total_players, entries })
+    }
+
+    pub fn get_leaderboard(&mut self, player_id: PlayerID, region: Region, mode: GameMode, offset: usize, limit: usize) -> Result<(), GameSyncError> {
+        let leaderboard = self.leaderboard_page(region, mode, offset, limit)?;
+        self.send_to_client(&player_id.to_string(), ServerEvent::Leaderboard(leaderboard))?;
+        Ok(())
+    }
+
+    pub fn get_leaderboard_position(&mut self, player_id: PlayerID, region: Region, mode: GameMode) -> Result<(), GameSyncError> {
+        if !self.data_store.is_valid_region(&region) {
+            return Err(GameSyncError::RegionError)
+        }
+        if mode != GameMode::Competitive {
+            return Err(GameSyncError::LeaderboardModeError)
+        }
+        let entry = self.data_store.get_leaderboard_position(region.clone(), mode, player_id);
+        self.send_to_client(&player_id.to_string(), ServerEvent::LeaderboardPosition(region, mode, entry))?;
+        Ok(())
+    }
+}
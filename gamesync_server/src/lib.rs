@@ -2,5 +2,21 @@ mod networking;
 pub mod server;
 mod store;
 mod error;
-mod lobby;
+pub mod lobby;
+mod matchmaking;
 pub mod server_params;
+pub mod rating;
+pub mod simulation;
+pub mod roles;
+pub mod ranked;
+pub mod penalties;
+pub mod auth;
+pub mod profiles;
+mod backfill;
+mod leaderboard;
+mod friends;
+mod blocking;
+pub mod chat;
+mod channels;
+pub mod filters;
+pub mod moderation;
@@ -1,9 +1,11 @@
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime};
 use serde::{Deserialize, Serialize};
-use strum_macros::EnumIter;
 use uuid::Uuid;
 
 pub type PlayerID = Uuid;
 pub type LobbyID = Uuid;
+pub type MatchID = Uuid;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Lobby {
@@ -12,7 +14,9 @@ pub struct Lobby {
     pub leader: PlayerID,
     pub status: LobbyStatus,
     pub player_list: Vec<PlayerID>,
-    pub queue_threshold: usize
+    pub queue_threshold: usize,
+    #[serde(default)]
+    pub parties: Vec<LobbyID> // Lobbies a server-assembled team was formed from. Empty for regular lobbies
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -20,13 +24,62 @@ pub struct LobbyParams {
     pub name: String,
 	pub visibility: Visibility,
     pub region: Region,
-    pub mode: GameMode
+    pub mode: GameMode,
+    #[serde(default)]
+    pub fallback_regions: Vec<Region>, // Other regions to search, in order of preference
+    #[serde(default)]
+    pub fallback_delay: Duration, // Time in queue before the fallback regions are searched
+    #[serde(default)]
+    pub hard_tags: HashMap<String, String>, // Matchmaking tags the opponent must not contradict, e.g. ruleset=hardcore
+    #[serde(default)]
+    pub soft_tags: HashMap<String, String> // Preferred matchmaking tags, e.g. map=desert
 }
 
-#[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq)]
+impl LobbyParams {
+    // Lobbies can only be matched if no tag key is required with different values by the two lobbies
+    pub fn hard_tags_agree(&self, other: &LobbyParams) -> bool {
+        self.hard_tags.iter().all(|(key, value)| other.hard_tags.get(key).is_none_or(|other_value| other_value == value))
+    }
+
+    // Number of preferred tags, of either lobby, that the other lobby shares
+    pub fn soft_tag_overlap(&self, other: &LobbyParams) -> usize {
+        let shared = |params: &LobbyParams, key: &String, value: &String| {
+            params.hard_tags.get(key) == Some(value) || params.soft_tags.get(key) == Some(value)
+        };
+        self.soft_tags.iter().filter(|(key, value)| shared(other, key, value)).count()
+            + other.soft_tags.iter().filter(|(key, value)| shared(self, key, value)).count()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct Player {
     pub player_id: Uuid,
-    pub rating: usize
+    pub rating: usize, // Server-authoritative. Only changed by match results or GameServer::set_rating
+    pub profile: PlayerProfile,
+    #[serde(default)]
+    pub role_preferences: Vec<String> // Ranked, most preferred first. Empty to fill any role
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct PlayerProfile { // Fields a client is allowed to edit for itself. Checked against the server's ProfileRules
+    #[serde(default)]
+    pub display_name: String, // Empty until the player sets one
+    #[serde(default)]
+    pub avatar_id: Option<String>,
+    pub metadata: HashMap<String, String>
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PlayerSummary { // Identifies a player in lobby and chat events
+    pub player_id: PlayerID,
+    pub display_name: String,
+    pub avatar_id: Option<String>
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum Credentials { // Checked by the server's AuthProvider on login
+    Token(String),
+    Password { username: String, password: String }
 }
 
 #[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq)]
@@ -35,17 +88,27 @@ pub enum Visibility {
 	Public
 }
 
-#[derive(Eq, Hash, PartialEq, Copy, Debug, Clone, EnumIter, Serialize, Deserialize)]
-pub enum Region {
-	NA,
-	EU,
-    SA,
-    MEA,
-    AS,
-    AU
+#[derive(Eq, Hash, PartialEq, Debug, Clone, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Region(String); // Region identifier, e.g. "us-east". Valid regions are set in ServerParams
+
+impl Region {
+    pub fn new(id: &str) -> Self {
+        Region(id.to_string())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
 }
 
-#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+impl From<&str> for Region {
+    fn from(id: &str) -> Self {
+        Region::new(id)
+    }
+}
+
+#[derive(Eq, Hash, PartialEq, Copy, Debug, Clone, Serialize, Deserialize)]
 pub enum GameMode {
     Casual,
     Competitive
@@ -55,5 +118,161 @@ pub enum GameMode {
 pub enum LobbyStatus {
     Idle,
     Queueing,
+    ReadyCheck,
     Ingame
-}
\ No newline at end of file
+}
+
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq)]
+pub enum MatchOutcome { // From the perspective of the reporting lobby
+    Win,
+    Loss,
+    Draw
+}
+
+impl MatchOutcome {
+    pub fn opposite(&self) -> MatchOutcome {
+        match self {
+            MatchOutcome::Win => MatchOutcome::Loss,
+            MatchOutcome::Loss => MatchOutcome::Win,
+            MatchOutcome::Draw => MatchOutcome::Draw
+        }
+    }
+
+    pub fn score(&self) -> f64 {
+        match self {
+            MatchOutcome::Win => 1.0,
+            MatchOutcome::Loss => 0.0,
+            MatchOutcome::Draw => 0.5
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MatchInfo {
+    pub match_id: MatchID,
+    pub region: Region, // Region chosen for the match. Can be one of the lobbies' fallback regions
+    pub opponent: Lobby,
+    #[serde(default)]
+    pub role: Option<String> // Role assigned to the receiving player. None if no role composition is configured
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueueStatus {
+    pub lobby_id: LobbyID,
+    pub time_in_queue: Duration,
+    pub position: usize, // 1 is the longest waiting lobby in the queue
+    pub lobbies_in_queue: usize,
+    pub estimated_wait: Option<Duration> // Remaining wait based on recent matches. None if there is no history yet
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Rank {
+    pub tier: String,
+    pub division: usize, // 1 is the highest division of the tier
+    pub progress: Option<usize> // Percent of the way to the next division. None in the highest tier
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SeasonResult {
+    pub season: usize,
+    pub rating: usize, // Rating at the end of the season, before the soft reset
+    pub rank: Option<Rank>, // None if the placement matches were not finished
+    pub matches_played: usize
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RankInfo {
+    pub player_id: PlayerID,
+    pub season: usize, // Current season
+    pub rank: Option<Rank>, // None until the placement matches are played
+    pub placement_matches_left: usize,
+    pub season_history: Vec<SeasonResult> // Oldest season first
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LeaderboardEntry {
+    pub position: usize, // 1 is the highest rated player
+    pub player_id: PlayerID,
+    pub rating: usize
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Leaderboard {
+    pub region: Region,
+    pub mode: GameMode,
+    pub total_players: usize,
+    pub entries: Vec<LeaderboardEntry> // Requested page, highest rating first
+}
+
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq)]
+pub enum Presence {
+    Offline,
+    Online, // Connected but not in a lobby
+    InLobby,
+    Queueing, // Queueing or in a ready check
+    InGame
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FriendInfo {
+    pub player_id: PlayerID,
+    pub presence: Presence
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FriendList {
+    pub friends: Vec<FriendInfo>,
+    pub incoming_requests: Vec<PlayerID>, // Players who sent a friend request to this player
+    pub outgoing_requests: Vec<PlayerID> // Players this player sent a friend request to
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DirectMessage {
+    pub message_id: u64, // Increases with every direct message sent on the server. Used to page conversations
+    pub from: PlayerID,
+    pub to: PlayerID,
+    pub message: String,
+    pub sent_at: SystemTime
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LobbyChatMessage {
+    pub from: PlayerSummary, // Sender as they were when the message was sent
+    pub message: String,
+    pub sent_at: SystemTime
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum ChannelKind {
+    Global,
+    Regional(Region), // Only joinable by players whose lobby is in that region
+    Guild(String), // Only joinable by players whose "guild" claim from the Authenticator matches
+    Custom // Created by a player and deleted when its last member leaves
+}
+
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq)]
+pub enum PostPermission {
+    Members,
+    Moderators // Announcement channels
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Channel {
+    pub name: String, // Unique. Used to join, leave and message the channel
+    pub kind: ChannelKind,
+    pub post: PostPermission,
+    pub owner: Option<PlayerID>, // Creator of a Custom channel. Only the owner can add or remove moderators
+    pub moderators: Vec<PlayerID> // Can kick members
+}
+
+#[derive(Debug, Clone)]
+pub struct Match {
+    pub match_id: MatchID,
+    pub region: Region,
+    pub mode: GameMode,
+    pub lobbies: (Lobby, Lobby), // Snapshot of both lobbies when the match was found
+    pub reports: Vec<(LobbyID, MatchOutcome)>, // Results reported by each lobby leader
+    pub deadline: Option<SystemTime>, // Set while the match is waiting on the ready check
+    pub accepted: Vec<PlayerID>, // Players who accepted the ready check
+    pub left: Vec<LobbyID>, // Lobbies that left the game. Their leaders can still report until the match is resolved
+}
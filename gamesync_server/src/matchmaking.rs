@@ -0,0 +1,382 @@
+use std::time::{Duration, SystemTime};
+use uuid::Uuid;
+use crate::error::{GameSyncError, print_error};
+use crate::lobby::{*};
+use crate::networking::{ServerEvent, ServerSignal, Websocket};
+use crate::penalties::Offense;
+
+impl Websocket {
+    /* READY CHECK */
+
+    pub(crate) fn propose_match(&mut self, mut game: Match, timeout: Duration) -> Result<(), GameSyncError> {
+        let deadline = SystemTime::now() + timeout;
+        game.deadline = Some(deadline);
+        let match_id = game.match_id;
+        let (lobby1, lobby2) = game.lobbies.clone();
+        self.data_store.add_match(game);
+        for mut lobby in [lobby1, lobby2] {
+            lobby.status = LobbyStatus::ReadyCheck;
+            self.data_store.edit_lobby(lobby.params.region.clone(), lobby.lobby_id, lobby.clone())?;
+            for player_id_lobby in lobby.player_list.iter() {
+                self.send_to_client(&player_id_lobby.to_string(), ServerEvent::MatchProposed(match_id, deadline))?;
+            }
+        }
+        self.handler.signals().send_with_timer(ServerSignal::ReadyCheckExpired(match_id), timeout);
+        Ok(())
+    }
+
+    pub fn accept_match(&mut self, player_id: PlayerID, match_id: MatchID) -> Result<(), GameSyncError> {
+        let mut game = self.find_ready_check(player_id, match_id)?;
+        if game.accepted.contains(&player_id) {
+            return Ok(())
+        }
+        game.accepted.push(player_id);
+        let players: Vec<PlayerID> = game.lobbies.0.player_list.iter().chain(game.lobbies.1.player_list.iter()).copied().collect();
+        for player_id_match in players.iter() {
+            self.send_to_client(&player_id_match.to_string(), ServerEvent::MatchAccepted(match_id, player_id))?;
+        }
+        if players.iter().all(|player| game.accepted.contains(player)) {
+            self.start_match(game)?;
+        } else {
+            self.data_store.edit_match(match_id, game);
+        }
+        Ok(())
+    }
+
+    pub fn decline_match(&mut self, player_id: PlayerID, match_id: MatchID) -> Result<(), GameSyncError> {
+        let game = self.find_ready_check(player_id, match_id)?;
+        self.cancel_match(game, &[player_id])
+    }
+
+    pub(crate) fn expire_ready_check(&mut self, match_id: MatchID) -> Result<(), GameSyncError> {
+        match self.data_store.get_match(match_id) {
+            Some(game) if game.deadline.is_some() => { // Everyone who has not accepted yet is treated as declining
+                let declined: Vec<PlayerID> = game.lobbies.0.player_list.iter().chain(game.lobbies.1.player_list.iter())
+                    .filter(|player| !game.accepted.contains(player))
+                    .copied()
+                    .collect();
+                println!("Ready check for match {} expired", match_id);
+                self.cancel_match(game, &declined)
+            },
+            _ => Ok(()) // Match was already started or cancelled
+        }
+    }
+
+    pub(crate) fn decline_lobby_match(&mut self, player_id: PlayerID, lobby_id: LobbyID) -> Result<(), GameSyncError> {
+        if let Some(game) = self.data_store.get_lobby_match(lobby_id) {
+            if game.deadline.is_some() {
+                self.cancel_match(game, &[player_id])?;
+            }
+        }
+        Ok(())
+    }
+
+    // Lobbies with a declining player go back to Idle. The other lobbies are put back in their queue
+    fn cancel_match(&mut self, game: Match, declined: &[PlayerID]) -> Result<(), GameSyncError> {
+        println!("Match {} cancelled", game.match_id);
+        self.data_store.remove_match(game.match_id);
+
+        for lobby in [game.lobbies.0, game.lobbies.1] {
+            let region = lobby.params.region;
+            let mut lobby = match self.data_store.get_lobby(region.clone(), lobby.lobby_id) {
+                Some(lobby) => lobby,
+                None => continue // Lobby was deleted in the meantime
+            };
+            let lobby_declined = lobby.player_list.iter().any(|player| declined.contains(player));
+            if lobby_declined && !lobby.parties.is_empty() { // Only the parties that declined leave the queue
+                self.dissolve_team(lobby.lobby_id, LobbyStatus::Queueing)?;
+                for party_id in lobby.parties.iter() {
+                    let mut party = match self.data_store.get_lobby(region.clone(), *party_id) {
+                        Some(party) => party,
+                        None => continue
+                    };
+                    let party_declined = party.player_list.iter().any(|player| declined.contains(player));
+                    if party_declined {
+                        party.status = LobbyStatus::Idle;
+                        self.data_store.remove_party_lobby(region.clone(), party.params.mode, party.lobby_id);
+                        self.data_store.edit_lobby(region.clone(), party.lobby_id, party.clone())?;
+                    }
+                    for player_id_lobby in party.player_list.iter() {
+                        if declined.contains(player_id_lobby) {
+                            self.penalize(*player_id_lobby, Offense::Dodge, game.mode)?;
+                        }
+                        if let Err(e) = self.send_to_client(&player_id_lobby.to_string(), ServerEvent::MatchCancelled(game.match_id)) {
+                            print_error(e); // Player may be the one disconnecting
+                        }
+                        if party_declined {
+                            if let Err(e) = self.send_to_client(&player_id_lobby.to_string(), ServerEvent::QueueStopped(party.lobby_id)) {
+                                print_error(e); // Player may be the one disconnecting
+                            }
+                        }
+                    }
+                }
+            } else if lobby_declined {
+                lobby.status = LobbyStatus::Idle;
+                self.data_store.remove_queue_time(lobby.lobby_id);
+                self.data_store.edit_lobby(region, lobby.lobby_id, lobby.clone())?;
+                for player_id_lobby in lobby.player_list.iter() {
+                    if declined.contains(player_id_lobby) {
+                        self.penalize(*player_id_lobby, Offense::Dodge, game.mode)?;
+                    }
+                    if let Err(e) = self.send_to_client(&player_id_lobby.to_string(), ServerEvent::MatchCancelled(game.match_id)) {
+                        print_error(e); // Player may be the one disconnecting
+                    }
+                    if let Err(e) = self.send_to_client(&player_id_lobby.to_string(), ServerEvent::QueueStopped(lobby.lobby_id)) {
+                        print_error(e); // Player may be the one disconnecting
+                    }
+                }
+            } else {
+                lobby.status = LobbyStatus::Queueing;
+                self.data_store.edit_lobby(region.clone(), lobby.lobby_id, lobby.clone())?;
+                match lobby.params.mode {
+                    GameMode::Casual => {
+                        self.data_store.requeue_casual_lobby(region, lobby.clone());
+                    },
+                    GameMode::Competitive => { // Competitive queue is ordered by rating rather than arrival
+                        self.data_store.add_competitive_lobby(region, lobby.clone());
+                    }
+                }
+                for player_id_lobby in lobby.player_list.iter() {
+                    if let Err(e) = self.send_to_client(&player_id_lobby.to_string(), ServerEvent::MatchCancelled(game.match_id)) {
+                        print_error(e); // Keep requeueing for the other players
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn find_ready_check(&mut self, player_id: PlayerID, match_id: MatchID) -> Result<Match, GameSyncError> {
+        let game = self.find_match(match_id)?;
+        let in_match = game.lobbies.0.player_list.contains(&player_id) || game.lobbies.1.player_list.contains(&player_id);
+        if game.deadline.is_none() || !in_match {
+            return Err(GameSyncError::MatchAcceptError)
+        }
+        Ok(game)
+    }
+
+    /* QUEUE STATUS */
+
+    pub fn get_queue_status(&mut self, player_id: PlayerID, lobby_id: LobbyID) -> Result<(), GameSyncError> {
+        let lobby_id = self.data_store.get_party_team(lobby_id).unwrap_or(lobby_id);
+        let region = self.find_region_lobby(lobby_id)?;
+        let lobby = self.find_lobby(region, lobby_id)?;
+        if !lobby.player_list.contains(&player_id) {
+            return Err(GameSyncError::QueueStatusError)
+        }
+        let status = self.compute_queue_status(&lobby).ok_or(GameSyncError::QueueStatusError)?;
+        self.send_to_client(&player_id.to_string(), ServerEvent::QueueStatus(status))?;
+        Ok(())
+    }
+
+    pub(crate) fn push_queue_statuses(&mut self) {
+        for lobby_id in self.data_store.get_queued_lobby_ids() {
+            let lobby = match self.data_store.get_region_lobby(&lobby_id).and_then(|region| self.data_store.get_lobby(region, lobby_id)) {
+                Some(lobby) => lobby,
+                None => {
+                    self.data_store.remove_queue_time(lobby_id); // Lobby no longer exists
+                    continue
+                }
+            };
+            if let Some(status) = self.compute_queue_status(&lobby) {
+                for player_id_lobby in lobby.player_list.iter() {
+                    if let Err(e) = self.send_to_client(&player_id_lobby.to_string(), ServerEvent::QueueStatus(status.clone())) {
+                        print_error(e);
+                    }
+                }
+            }
+        }
+    }
+
+    fn compute_queue_status(&self, lobby: &Lobby) -> Option<QueueStatus> {
+        if lobby.status != LobbyStatus::Queueing {
+            return None
+        }
+        let (region, mode) = (lobby.params.region.clone(), lobby.params.mode);
+        let queued_at = self.data_store.get_queue_time(lobby.lobby_id)?;
+        let (position, lobbies_in_queue) = self.data_store.get_queue_position(region.clone(), mode, lobby.lobby_id)?;
+        let time_in_queue = SystemTime::now().duration_since(queued_at).unwrap_or_default();
+        let estimated_wait = self.data_store.get_average_wait_time(region, mode)
+            .map(|average| average.saturating_sub(time_in_queue));
+        Some(QueueStatus { lobby_id: lobby.lobby_id, time_in_queue, position, lobbies_in_queue, estimated_wait })
+    }
+
+    /* PARTY FORMATION */
+
+    // Fills the remaining slots of a partial lobby with other queued partial lobbies, in arrival order.
+    // Competitive parties must be within both lobbies' thresholds of the first lobby's average rating,
+    // hard tags must agree between all parties, and the players must fit the role composition if one is configured
+    pub(crate) fn form_team(&mut self, region: Region, lobby_id: LobbyID) -> Result<Option<LobbyID>, GameSyncError> {
+        let first = self.find_lobby(region.clone(), lobby_id)?;
+        let mode = first.params.mode;
+        let first_rating = self.data_store.get_lobby_average_rating(region.clone(), lobby_id);
+        let mut open_slots = self.data_store.lobby_size().saturating_sub(first.player_list.len());
+        let mut parties = vec![first.clone()];
+        for party_id in self.data_store.get_party_lobbies(region.clone(), mode) {
+            if open_slots == 0 {
+                break;
+            }
+            let party = match self.data_store.get_lobby(region.clone(), party_id) {
+                Some(party) if party_id != lobby_id && party.player_list.len() <= open_slots => party,
+                _ => continue
+            };
+            if !parties.iter().all(|chosen| chosen.params.hard_tags_agree(&party.params)) {
+                continue;
+            }
+            if mode == GameMode::Competitive {
+                let gap = first_rating.abs_diff(self.data_store.get_lobby_average_rating(region.clone(), party_id));
+                if gap > first.queue_threshold || gap > party.queue_threshold {
+                    continue;
+                }
+            }
+            let players: Vec<PlayerID> = parties.iter().chain([&party]).flat_map(|party| party.player_list.iter().copied()).collect();
+            if self.get_role_assignment(&players).is_none() { // Team must still fit the role composition
+                continue;
+            }
+            open_slots -= party.player_list.len();
+            parties.push(party);
+        }
+        if open_slots > 0 {
+            return Ok(None)
+        }
+
+        // Team is led by, and waits in queue from the time of, the party that queued first
+        let earliest = parties.iter()
+            .filter_map(|party| Some((self.data_store.get_queue_time(party.lobby_id)?, party.leader)))
+            .min();
+        let (queued_at, leader) = earliest.unwrap_or((SystemTime::now(), first.leader));
+        let team_id = Uuid::new_v4();
+        let mut params = LobbyParams { visibility: Visibility::Private, ..first.params.clone() };
+        for party in parties.iter().skip(1) { // Team is matched on the tags of all its parties
+            for (key, value) in party.params.hard_tags.iter() {
+                params.hard_tags.entry(key.clone()).or_insert_with(|| value.clone());
+            }
+            for (key, value) in party.params.soft_tags.iter() {
+                params.soft_tags.entry(key.clone()).or_insert_with(|| value.clone());
+            }
+        }
+        let team = Lobby {
+            lobby_id: team_id,
+            params,
+            leader,
+            status: LobbyStatus::Queueing,
+            player_list: parties.iter().flat_map(|party| party.player_list.iter().copied()).collect(),
+            queue_threshold: first.queue_threshold,
+            parties: parties.iter().map(|party| party.lobby_id).collect()
+        };
+        println!("Team {} formed from lobbies {:?}", team_id, team.parties);
+        for party in parties.iter() {
+            self.data_store.remove_party_lobby(region.clone(), mode, party.lobby_id);
+            self.data_store.set_party_team(party.lobby_id, team_id);
+        }
+        self.data_store.create_lobby(region.clone(), team_id, team.clone());
+        self.data_store.create_region_lobby(team_id, region.clone());
+        self.data_store.set_queue_time(team_id, queued_at);
+        match mode {
+            GameMode::Casual => {
+                self.data_store.add_casual_lobby(region, team.clone());
+            },
+            GameMode::Competitive => {
+                self.data_store.add_competitive_lobby(region, team.clone());
+            }
+        }
+        for player_id_lobby in team.player_list.iter() {
+            self.data_store.edit_player(*player_id_lobby, None, Some(team_id));
+            self.send_to_client(&player_id_lobby.to_string(), ServerEvent::TeamFormed(team.clone()))?;
+        }
+        Ok(Some(team_id))
+    }
+
+    // Deletes a team lobby and moves its players back to their own lobbies with the given status.
+    // Queueing parties go back to the party queue
+    pub(crate) fn dissolve_team(&mut self, team_id: LobbyID, status: LobbyStatus) -> Result<(), GameSyncError> {
+        let region = self.find_region_lobby(team_id)?;
+        let team = self.find_lobby(region.clone(), team_id)?;
+        match team.params.mode {
+            GameMode::Casual => {
+                self.data_store.remove_casual_lobby(region.clone(), team_id);
+            },
+            GameMode::Competitive => {
+                self.data_store.remove_competitive_lobby(region.clone(), team_id);
+            }
+        }
+        self.data_store.delete_lobby(region.clone(), team_id)?;
+        self.data_store.delete_region_lobby(team_id)?;
+        println!("Team {} disbanded", team_id);
+
+        for party_id in team.parties.iter() {
+            self.data_store.remove_party_team(*party_id);
+            let mut party = match self.data_store.get_lobby(region.clone(), *party_id) {
+                Some(party) => party,
+                None => continue // Party lobby was deleted in the meantime
+            };
+            party.status = status;
+            self.data_store.edit_lobby(region.clone(), party.lobby_id, party.clone())?;
+            if status == LobbyStatus::Queueing {
+                self.data_store.set_queue_time(party.lobby_id, SystemTime::now());
+                self.data_store.add_party_lobby(region.clone(), party.params.mode, party.lobby_id);
+            }
+            for player_id_lobby in party.player_list.iter() {
+                self.data_store.edit_player(*player_id_lobby, None, Some(party.lobby_id));
+                if let Err(e) = self.send_to_client(&player_id_lobby.to_string(), ServerEvent::TeamDisbanded(team_id)) {
+                    print_error(e); // Player may be the one disconnecting
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Breaks up the player's team, if any, before they leave. Returns the lobby the player queued with
+    pub(crate) fn leave_team(&mut self, player_id: PlayerID, lobby_id: LobbyID) -> Result<LobbyID, GameSyncError> {
+        let team_id = self.data_store.get_party_team(lobby_id).unwrap_or(lobby_id);
+        let region = self.find_region_lobby(team_id)?;
+        let team = self.find_lobby(region.clone(), team_id)?;
+        if team.parties.is_empty() { // Not a team
+            return Ok(lobby_id)
+        }
+        let party_id = team.parties.iter()
+            .find(|party_id| self.data_store.get_lobby(region.clone(), **party_id).is_some_and(|party| party.player_list.contains(&player_id)))
+            .copied()
+            .ok_or(GameSyncError::TeamMemberError)?;
+
+        if team.status == LobbyStatus::ReadyCheck { // Declining breaks up the team and takes the player's party out of the queue
+            self.decline_lobby_match(player_id, team_id)?;
+        }
+        if self.data_store.get_party_team(party_id).is_some() && team.status == LobbyStatus::Ingame {
+            // The team can not play on without a lobby in the match, so its side of the match ends for every party
+            if let Some(game) = self.data_store.get_lobby_match(team_id) {
+                self.data_store.leave_match(game.match_id, team_id);
+            }
+            for player_id_team in team.player_list.iter() {
+                if let Err(e) = self.send_to_client(&player_id_team.to_string(), ServerEvent::LeftGame(team_id)) {
+                    print_error(e); // Player may be the one disconnecting
+                }
+            }
+            self.dissolve_team(team_id, LobbyStatus::Idle)?;
+        } else if self.data_store.get_party_team(party_id).is_some() { // Other parties keep queueing
+            self.dissolve_team(team_id, LobbyStatus::Queueing)?;
+        }
+        Ok(party_id)
+    }
+
+    /* CROSS-REGION FALLBACK */
+
+    pub(crate) fn check_fallback_match(&mut self, player_id: PlayerID, lobby: &Lobby, threshold: usize) -> Result<(), GameSyncError> {
+        let time_in_queue = self.data_store.get_queue_time(lobby.lobby_id)
+            .and_then(|queued_at| SystemTime::now().duration_since(queued_at).ok())
+            .unwrap_or_default();
+        if time_in_queue >= lobby.params.fallback_delay {
+            for region in lobby.params.fallback_regions.iter().filter(|region| **region != lobby.params.region) {
+                let lobbies = match lobby.params.mode {
+                    GameMode::Casual => self.data_store.check_casual_fallback(region.clone(), lobby),
+                    GameMode::Competitive => self.data_store.check_competitive_fallback(region.clone(), lobby, threshold)
+                };
+                if let Some(lobbies) = lobbies { // Match is played in the region of the lobby that was found
+                    return self.finalize_match(region.clone(), lobbies, threshold)
+                }
+            }
+        }
+        self.send_to_client(&player_id.to_string(), ServerEvent::MatchNotFound)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,95 @@
+use std::time::{Duration, SystemTime};
+use uuid::Uuid;
+use crate::error::GameSyncError;
+use crate::filters::MessageKind;
+use crate::lobby::{*};
+use crate::networking::{ServerEvent, Websocket};
+
+const MAX_REPORT_REASON_LENGTH: usize = 500; // In characters
+
+// Chat message kept as context for reports
+#[derive(Debug, Clone)]
+pub struct ReportedMessage {
+    pub kind: MessageKind,
+    pub message: String, // Text as sent, after the server's filters
+    pub sent_at: SystemTime,
+}
+
+#[derive(Debug, Clone)]
+pub struct Report {
+    pub report_id: Uuid,
+    pub reporter: PlayerID,
+    pub reported: PlayerID,
+    pub reason: String,
+    pub created_at: SystemTime,
+    pub recent_messages: Vec<ReportedMessage>, // Reported player's last messages when the report was filed, oldest first
+}
+
+impl Websocket {
+    // Muted players can still read the lobby chat. Mutes outlast leaving and rejoining the lobby
+    pub fn mute_player(&mut self, player_id: PlayerID, lobby_id: LobbyID, target_id: PlayerID, duration: Duration) -> Result<(), GameSyncError> {
+        let region = self.find_region_lobby(lobby_id)?;
+        let lobby = self.find_lobby(region, lobby_id)?;
+        if lobby.leader != player_id {
+            return Err(GameSyncError::LobbyOwnerError)
+        }
+        if target_id == player_id || !lobby.player_list.contains(&target_id) {
+            return Err(GameSyncError::LobbyMuteError)
+        }
+        let duration = duration.min(self.data_store.chat_params().max_mute);
+        let Some(until) = SystemTime::now().checked_add(duration) else {
+            return Err(GameSyncError::LobbyMuteError)
+        };
+        self.data_store.mute_player(lobby_id, target_id, until);
+        for player_id_lobby in lobby.player_list.iter() {
+            self.send_to_client(&player_id_lobby.to_string(), ServerEvent::PlayerMuted(lobby_id, target_id, until))?;
+        }
+        Ok(())
+    }
+
+    pub fn unmute_player(&mut self, player_id: PlayerID, lobby_id: LobbyID, target_id: PlayerID) -> Result<(), GameSyncError> {
+        let region = self.find_region_lobby(lobby_id)?;
+        let lobby = self.find_lobby(region, lobby_id)?;
+        if lobby.leader != player_id {
+            return Err(GameSyncError::LobbyOwnerError)
+        }
+        if !self.data_store.unmute_player(lobby_id, target_id) {
+            return Err(GameSyncError::LobbyMuteError)
+        }
+        for player_id_lobby in lobby.player_list.iter() {
+            self.send_to_client(&player_id_lobby.to_string(), ServerEvent::PlayerUnmuted(lobby_id, target_id))?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn check_lobby_mute(&mut self, player_id: PlayerID, lobby_id: LobbyID) -> Result<(), GameSyncError> {
+        if let Some(until) = self.data_store.get_mute(lobby_id, player_id) {
+            self.send_to_client(&player_id.to_string(), ServerEvent::MessageRejected("Muted in this lobby".to_string()))?;
+            return Err(GameSyncError::LobbyMutedError { until })
+        }
+        Ok(())
+    }
+
+    // One open report per reporter and reported player
+    pub fn report_player(&mut self, player_id: PlayerID, target_id: PlayerID, reason: String) -> Result<(), GameSyncError> {
+        if target_id == player_id || reason.chars().count() > MAX_REPORT_REASON_LENGTH {
+            return Err(GameSyncError::ReportError)
+        }
+        if self.data_store.get_player_profile(target_id).is_none() {
+            return Err(GameSyncError::PlayerFindError)
+        }
+        let report = Report {
+            report_id: Uuid::new_v4(),
+            reporter: player_id,
+            reported: target_id,
+            reason,
+            created_at: SystemTime::now(),
+            recent_messages: self.data_store.get_recent_messages(target_id),
+        };
+        if !self.data_store.add_report(report) {
+            return Err(GameSyncError::ReportError)
+        }
+        self.send_to_client(&player_id.to_string(), ServerEvent::PlayerReported(target_id))?;
+        Ok(())
+    }
+}
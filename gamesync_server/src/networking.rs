@@ -1,5 +1,7 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, SystemTime};
 use crate::server_params::ServerParams;
 use crate::store::DataStore;
 use message_io::network::{Endpoint, NetEvent, SendStatus, Transport};
@@ -8,26 +10,66 @@ use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 use crate::error::{GameSyncError, print_error};
 use crate::lobby::{*};
+use crate::penalties::Offense;
+use crate::filters::MessageKind;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub enum ServerEvent {
-    UserMessage(PlayerID, String), // From, Msg
+    UserMessage(PlayerSummary, String), // From, Msg
     SelfPlayer(String),
     NewPlayer(String),
     LobbyCreated(Lobby), // Lobby
-    LobbyJoined(PlayerID, LobbyID), // Lobby ID
+    LobbyJoined(PlayerSummary, LobbyID), // Player who joined, Lobby ID
     LobbyDeleted(LobbyID), // Lobby ID
     LobbyLeft(PlayerID, LobbyID), // Lobby ID
     LobbyInvited(LobbyID), // Lobby ID
     PublicLobbies(Vec<Lobby>),
     PlayerEdited(PlayerID), // Player ID
-    LobbyMessage(PlayerID, String), // From, Msg
+    LobbyMessage(PlayerSummary, String), // From, Msg
     LobbyQueued(LobbyID),
-    MatchFound(Lobby), // Opponent lobby
+    MatchFound(MatchInfo), // Match ID, Region the match is played in, Opponent lobby
     MatchNotFound,
     QueueStopped(LobbyID),
     LeftGame(LobbyID),
-    LobbyInfo(Lobby)
+    LobbyInfo(Lobby, Vec<PlayerSummary>), // Lobby, Roster
+    RatingChanged(usize, usize), // Old, New
+    MatchResultDisputed(MatchID),
+    MatchProposed(MatchID, SystemTime), // Match ID, Deadline to accept
+    MatchAccepted(MatchID, PlayerID), // Match ID, Player who accepted
+    MatchCancelled(MatchID),
+    QueueStatus(QueueStatus),
+    Pong(u64, Vec<Region>), // Echoed ping ID, Regions served by this server
+    TeamFormed(Lobby), // Temporary team lobby assembled from partial lobbies
+    TeamDisbanded(LobbyID), // Team lobby ID. Players are back in the lobby they queued with
+    RankInfo(RankInfo),
+    SeasonStarted(usize), // New season number. Ratings have been soft reset
+    Leaderboard(Leaderboard),
+    LeaderboardPosition(Region, GameMode, Option<LeaderboardEntry>), // None if the player is not on this leaderboard
+    QueueBanned(SystemTime), // Lockout end after a dodge or abandon
+    BackfillJoined(MatchID, LobbyID, PlayerID), // Match ID, Lobby the player was added to, Player ID
+    FriendRequestReceived(PlayerID), // Sender ID
+    FriendAdded(FriendInfo),
+    FriendRemoved(PlayerID), // Former friend ID
+    FriendPresenceChanged(PlayerID, Presence), // Friend ID, New presence
+    Friends(FriendList),
+    PlayerBlocked(PlayerID),
+    PlayerUnblocked(PlayerID),
+    LoggedIn(PlayerID), // Persistent player ID of the account. Replaces the ID sent in SelfPlayer
+    Authenticated(PlayerID, HashMap<String, String>), // Player ID, Claims
+    AuthRejected(String), // Reason. The connection is closed
+    Profiles(Vec<(PlayerID, PlayerProfile)>),
+    Conversation(PlayerID, Vec<DirectMessage>), // Other player, Messages oldest first
+    OfflineMessages(Vec<DirectMessage>), // Direct messages received while offline, oldest first
+    LobbyChatHistory(LobbyID, Vec<LobbyChatMessage>), // Lobby ID, Recent messages oldest first
+    ChannelJoined(Channel),
+    ChannelLeft(String), // Channel name. Also sent when kicked or when the server removes the channel
+    ChannelMessage(String, PlayerSummary, String), // Channel name, From, Msg
+    ChannelUpdated(Channel), // Moderators changed
+    Channels(Vec<Channel>), // Channels the player can join
+    MessageRejected(String), // Reason a chat message from this player was dropped by the server's filters or a mute
+    PlayerMuted(LobbyID, PlayerID, SystemTime), // Lobby ID, Muted player, Mute end
+    PlayerUnmuted(LobbyID, PlayerID),
+    PlayerReported(PlayerID), // Reported player. The report was filed
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -41,19 +83,59 @@ pub enum ClientEvent {
     LeaveLobby(LobbyID),
     InviteLobby(LobbyID, PlayerID),  // Sender ID, Lobby ID, Invitee ID
     GetPublicLobbies(Region),
-    EditPlayer(Player),
+    EditPlayer(PlayerProfile), // Always edits the sender's own profile
     MessageLobby(LobbyID, String), // Sender ID, Lobby ID, Message
     QueueLobby(LobbyID),
     CheckMatch(LobbyID, Option<usize>), // Sender ID, Lobby ID, Threshold
     StopQueue(LobbyID),
     LeaveGameAsLobby(LobbyID),
     GetLobbyInfo(LobbyID),
+    ReportMatchResult(MatchID, MatchOutcome),
+    AcceptMatch(MatchID),
+    DeclineMatch(MatchID),
+    GetQueueStatus(LobbyID),
+    Ping(u64), // Ping ID
+    SetRolePreferences(Vec<String>), // Ranked roles for the sender
+    GetRankInfo(PlayerID),
+    GetLeaderboard(Region, GameMode, usize, usize), // Region, Mode, Offset, Limit
+    GetMyLeaderboardPosition(Region, GameMode),
+    SendFriendRequest(PlayerID), // Target ID
+    AcceptFriendRequest(PlayerID), // Sender ID
+    DeclineFriendRequest(PlayerID), // Sender ID
+    RemoveFriend(PlayerID), // Friend ID
+    GetFriends,
+    BlockPlayer(PlayerID),
+    UnblockPlayer(PlayerID),
+    Login(Credentials),
+    Authenticate { token: String }, // First message of every connection when the server has an Authenticator
+    GetProfiles(Vec<PlayerID>),
+    GetConversation { with: PlayerID, before: Option<u64>, limit: usize }, // before is a message ID. None gets the latest messages
+    GetLobbyChatHistory(LobbyID),
+    CreateChannel(String, PostPermission), // Channel name, Who can post
+    JoinChannel(String),
+    LeaveChannel(String),
+    MessageChannel(String, String), // Channel name, Message
+    KickFromChannel(String, PlayerID), // Channel name, Member ID
+    SetChannelModerator(String, PlayerID, bool), // Channel name, Member ID, Moderator
+    GetChannels,
+    MutePlayer(LobbyID, PlayerID, Duration), // Lobby ID, Member ID, Mute length
+    UnmutePlayer(LobbyID, PlayerID),
+    ReportPlayer(PlayerID, String), // Reported player, Reason
 }
 
+// Internal events the server schedules for itself
+#[derive(Debug)]
+pub enum ServerSignal {
+    ReadyCheckExpired(MatchID),
+    MatchExpired(MatchID),
+    QueueStatusTick,
+}
+
+#[derive(Clone)]
 pub struct Websocket {
-    handler: NodeHandler<ClientEvent>,
-    listener: Option<NodeListener<ClientEvent>>,
-    data_store: DataStore,
+    pub(crate) handler: NodeHandler<ServerSignal>,
+    pub(crate) listener: Arc<Mutex<Option<NodeListener<ServerSignal>>>>,
+    pub(crate) data_store: DataStore,
 }
 
 impl Websocket {
@@ -63,10 +145,14 @@ impl Websocket {
         let data_store = DataStore::new(server_params);
         println!("Server set up");
 
-        Ok(Websocket { handler, listener: Some(listener), data_store })
+        Ok(Websocket { handler, listener: Arc::new(Mutex::new(Some(listener))), data_store })
     }
     pub fn process_messages(&mut self) {
-        self.listener.take().unwrap().for_each(move |event|
+        let listener = self.listener.lock().unwrap().take().unwrap(); // Only one thread processes messages
+        if let Some(interval) = self.data_store.queue_status_interval() {
+            self.handler.signals().send_with_timer(ServerSignal::QueueStatusTick, interval);
+        }
+        listener.for_each(move |event|
             match event {
                 NodeEvent::Network(net_event) => match net_event {
                     NetEvent::Accepted(endpoint, _) | NetEvent::Connected(endpoint, _) => {
@@ -74,14 +160,24 @@ impl Websocket {
                             Ok(_) => {},
                             Err(e) => print_error(e),
                         }
+                        self.flush_presence();
                     }
                     NetEvent::Message(endpoint, message) => {
                         let msg = serde_json::from_slice(&message).unwrap();
 
-                        match self.handle_messages(endpoint, msg) {
+                        let result = if self.data_store.is_pending_endpoint(endpoint) { // Only allowed to authenticate
+                            self.handle_unauthenticated(endpoint, msg)
+                        } else {
+                            self.handle_messages(endpoint, msg)
+                        };
+                        match result {
                             Ok(_) => {},
                             Err(e) => print_error(e),
                         }
+                        self.flush_presence();
+                    }
+                    NetEvent::Disconnected(endpoint) if self.data_store.remove_pending_endpoint(endpoint) => {
+                        println!("Unauthenticated connection {} closed", endpoint);
                     }
                     NetEvent::Disconnected(endpoint) => {
                         println!("User {} disconnected", self.data_store.get_user(endpoint).unwrap());
@@ -93,9 +189,16 @@ impl Websocket {
                         }
                         self.data_store.remove_user_endpoint(endpoint); // Do this at the end
                         println!("Cleaned up datastructures");
+                        self.flush_presence();
                     }
                 },
-                _ => {}
+                NodeEvent::Signal(signal) => {
+                    match self.handle_signal(signal) {
+                        Ok(_) => {},
+                        Err(e) => print_error(e),
+                    }
+                    self.flush_presence();
+                }
             }
         );
     }
@@ -108,13 +211,11 @@ impl Websocket {
         match event {
             ClientEvent::Broadcast(message) => {
                 println!("Broadcasting message: {}", message);
-                let event = ServerEvent::UserMessage(player_id, message);
-                self.send_to_all_clients(endpoint, event)?;
+                self.broadcast(player_id, endpoint, message)?;
             }
             ClientEvent::SendTo(uuid, message) => {
                 println!("To: {} Message: {}", uuid, message);
-                let event = ServerEvent::UserMessage(player_id, message);
-                self.send_to_client(&uuid, event)?;
+                self.send_direct_message(player_id, Uuid::parse_str(&uuid)?, message)?;
             }
             ClientEvent::CreateLobby(lobby_params) => {
                 println!("CreateLobby => Player ID: {:?} LobbyParams: {:?}", player_id, lobby_params);
@@ -140,9 +241,9 @@ impl Websocket {
                 println!("GetPublicLobbies => Player ID: {:?} Region: {:?}", player_id, region);
                 self.get_public_lobbies(player_id, region)?;
             },
-            ClientEvent::EditPlayer(player) => {
-                println!("EditPlayer => Player: {:?}", player);
-                self.edit_player(player)?;
+            ClientEvent::EditPlayer(profile) => {
+                println!("EditPlayer => Player ID: {:?} Profile: {:?}", player_id, profile);
+                self.edit_player(player_id, profile)?;
             },
             ClientEvent::MessageLobby(lobby_id, message) => {
                 println!("MessageLobby => Player ID: {:?} LobbyID: {:?} Message: {}", player_id, lobby_id, message);
@@ -169,12 +270,156 @@ impl Websocket {
                 println!("GetLobbyInfo => Lobby ID: {:?}", lobby_id);
                 self.get_lobby_info(player_id, lobby_id)?;
             },
+            ClientEvent::ReportMatchResult(match_id, outcome) => {
+                println!("ReportMatchResult => Player ID: {:?} MatchID: {:?} Outcome: {:?}", player_id, match_id, outcome);
+                self.report_match_result(player_id, match_id, outcome)?;
+            },
+            ClientEvent::AcceptMatch(match_id) => {
+                println!("AcceptMatch => Player ID: {:?} MatchID: {:?}", player_id, match_id);
+                self.accept_match(player_id, match_id)?;
+            },
+            ClientEvent::DeclineMatch(match_id) => {
+                println!("DeclineMatch => Player ID: {:?} MatchID: {:?}", player_id, match_id);
+                self.decline_match(player_id, match_id)?;
+            },
+            ClientEvent::GetQueueStatus(lobby_id) => {
+                println!("GetQueueStatus => Player ID: {:?} LobbyID: {:?}", player_id, lobby_id);
+                self.get_queue_status(player_id, lobby_id)?;
+            },
+            ClientEvent::SetRolePreferences(roles) => {
+                println!("SetRolePreferences => Player ID: {:?} Roles: {:?}", player_id, roles);
+                self.set_role_preferences(player_id, roles)?;
+            },
+            ClientEvent::GetRankInfo(target_id) => {
+                println!("GetRankInfo => Player ID: {:?} Target ID: {:?}", player_id, target_id);
+                self.send_rank_info(player_id, target_id)?;
+            },
+            ClientEvent::GetLeaderboard(region, mode, offset, limit) => {
+                println!("GetLeaderboard => Player ID: {:?} Region: {:?} Mode: {:?} Offset: {} Limit: {}", player_id, region, mode, offset, limit);
+                self.get_leaderboard(player_id, region, mode, offset, limit)?;
+            },
+            ClientEvent::GetMyLeaderboardPosition(region, mode) => {
+                println!("GetMyLeaderboardPosition => Player ID: {:?} Region: {:?} Mode: {:?}", player_id, region, mode);
+                self.get_leaderboard_position(player_id, region, mode)?;
+            },
+            ClientEvent::SendFriendRequest(target_id) => {
+                println!("SendFriendRequest => Player ID: {:?} Target ID: {:?}", player_id, target_id);
+                self.send_friend_request(player_id, target_id)?;
+            },
+            ClientEvent::AcceptFriendRequest(sender_id) => {
+                println!("AcceptFriendRequest => Player ID: {:?} Sender ID: {:?}", player_id, sender_id);
+                self.accept_friend_request(player_id, sender_id)?;
+            },
+            ClientEvent::DeclineFriendRequest(sender_id) => {
+                println!("DeclineFriendRequest => Player ID: {:?} Sender ID: {:?}", player_id, sender_id);
+                self.decline_friend_request(player_id, sender_id)?;
+            },
+            ClientEvent::RemoveFriend(friend_id) => {
+                println!("RemoveFriend => Player ID: {:?} Friend ID: {:?}", player_id, friend_id);
+                self.remove_friend(player_id, friend_id)?;
+            },
+            ClientEvent::GetFriends => {
+                println!("GetFriends => Player ID: {:?}", player_id);
+                self.get_friends(player_id)?;
+            },
+            ClientEvent::BlockPlayer(target_id) => {
+                println!("BlockPlayer => Player ID: {:?} Target ID: {:?}", player_id, target_id);
+                self.block_player(player_id, target_id)?;
+            },
+            ClientEvent::UnblockPlayer(target_id) => {
+                println!("UnblockPlayer => Player ID: {:?} Target ID: {:?}", player_id, target_id);
+                self.unblock_player(player_id, target_id)?;
+            },
+            ClientEvent::Login(credentials) => { // Credentials are not logged
+                println!("Login => Player ID: {:?}", player_id);
+                self.login(endpoint, player_id, credentials)?;
+            },
+            ClientEvent::Authenticate { .. } => {
+                return Err(GameSyncError::AuthenticatedError)
+            },
+            ClientEvent::GetProfiles(player_ids) => {
+                println!("GetProfiles => Player ID: {:?} Players: {:?}", player_id, player_ids);
+                self.get_profiles(player_id, player_ids)?;
+            },
+            ClientEvent::GetConversation { with, before, limit } => {
+                println!("GetConversation => Player ID: {:?} With: {:?} Before: {:?} Limit: {}", player_id, with, before, limit);
+                self.get_conversation(player_id, with, before, limit)?;
+            },
+            ClientEvent::GetLobbyChatHistory(lobby_id) => {
+                println!("GetLobbyChatHistory => Player ID: {:?} LobbyID: {:?}", player_id, lobby_id);
+                self.get_lobby_chat_history(player_id, lobby_id)?;
+            },
+            ClientEvent::CreateChannel(name, post) => {
+                println!("CreateChannel => Player ID: {:?} Channel: {} Post: {:?}", player_id, name, post);
+                self.create_channel(player_id, name, post)?;
+            },
+            ClientEvent::JoinChannel(name) => {
+                println!("JoinChannel => Player ID: {:?} Channel: {}", player_id, name);
+                self.join_channel(player_id, name)?;
+            },
+            ClientEvent::LeaveChannel(name) => {
+                println!("LeaveChannel => Player ID: {:?} Channel: {}", player_id, name);
+                self.leave_channel(player_id, name)?;
+            },
+            ClientEvent::MessageChannel(name, message) => {
+                println!("MessageChannel => Player ID: {:?} Channel: {} Message: {}", player_id, name, message);
+                self.message_channel(player_id, name, message)?;
+            },
+            ClientEvent::KickFromChannel(name, target_id) => {
+                println!("KickFromChannel => Player ID: {:?} Channel: {} Target ID: {:?}", player_id, name, target_id);
+                self.kick_from_channel(player_id, name, target_id)?;
+            },
+            ClientEvent::SetChannelModerator(name, target_id, moderator) => {
+                println!("SetChannelModerator => Player ID: {:?} Channel: {} Target ID: {:?} Moderator: {}", player_id, name, target_id, moderator);
+                self.set_channel_moderator(player_id, name, target_id, moderator)?;
+            },
+            ClientEvent::GetChannels => {
+                println!("GetChannels => Player ID: {:?}", player_id);
+                self.get_channels(player_id)?;
+            },
+            ClientEvent::MutePlayer(lobby_id, target_id, duration) => {
+                println!("MutePlayer => Player ID: {:?} LobbyID: {:?} Target ID: {:?} Duration: {:?}", player_id, lobby_id, target_id, duration);
+                self.mute_player(player_id, lobby_id, target_id, duration)?;
+            },
+            ClientEvent::UnmutePlayer(lobby_id, target_id) => {
+                println!("UnmutePlayer => Player ID: {:?} LobbyID: {:?} Target ID: {:?}", player_id, lobby_id, target_id);
+                self.unmute_player(player_id, lobby_id, target_id)?;
+            },
+            ClientEvent::ReportPlayer(target_id, reason) => {
+                println!("ReportPlayer => Player ID: {:?} Target ID: {:?} Reason: {}", player_id, target_id, reason);
+                self.report_player(player_id, target_id, reason)?;
+            },
+            ClientEvent::Ping(ping_id) => { // Not logged to keep latency measurements cheap
+                self.send_to_client(&player_id.to_string(), ServerEvent::Pong(ping_id, self.data_store.served_regions()))?;
+            },
         }
 
         Ok(())
     }
 
+    fn handle_signal(&mut self, signal: ServerSignal) -> Result<(), GameSyncError> {
+        match signal {
+            ServerSignal::ReadyCheckExpired(match_id) => {
+                self.expire_ready_check(match_id)?;
+            },
+            ServerSignal::MatchExpired(match_id) => {
+                self.expire_match(match_id);
+            },
+            ServerSignal::QueueStatusTick => {
+                self.push_queue_statuses();
+                if let Some(interval) = self.data_store.queue_status_interval() {
+                    self.handler.signals().send_with_timer(ServerSignal::QueueStatusTick, interval);
+                }
+            }
+        }
+        Ok(())
+    }
+
     pub fn handle_new_connections(&mut self, endpoint: Endpoint) -> Result<(), GameSyncError> {
+        if self.data_store.authenticator().is_some() { // Becomes a player once its Authenticate is accepted
+            self.data_store.add_pending_endpoint(endpoint);
+            return Ok(())
+        }
         // send user info to new connected client
         let id = self.data_store.add_user_endpoint(endpoint).to_string();
         println!("Connection from {}", id);
@@ -188,8 +433,8 @@ impl Websocket {
 
         // Default player
         let player_id = Uuid::parse_str(&id)?;
-        let rating = 0;
-        let player = Player { player_id, rating };
+        let rating = self.data_store.initial_rating();
+        let player = Player { player_id, rating, profile: PlayerProfile::default(), role_preferences: Vec::new() };
         self.data_store.add_player(player_id, player);
         Ok(())
     }
@@ -236,6 +481,10 @@ impl Websocket {
                 return Err(GameSyncError::LobbyCreateError);
             },
             None => {
+                let mut regions = std::iter::once(&lobby_params.region).chain(lobby_params.fallback_regions.iter());
+                if !regions.all(|region| self.data_store.is_valid_region(region)) {
+                    return Err(GameSyncError::RegionError);
+                }
                 let lobby_id = Uuid::new_v4();
                 let lobby = Lobby {
                     lobby_id: lobby_id.clone(),
@@ -243,10 +492,11 @@ impl Websocket {
                     leader: player_id,
                     status: LobbyStatus::Idle,
                     player_list: vec![player_id],
-                    queue_threshold: 0
+                    queue_threshold: 0,
+                    parties: Vec::new()
                 };
-                self.data_store.create_lobby(lobby_params.region, lobby_id, lobby.clone());
-                self.data_store.create_region_lobby(lobby_id, lobby_params.region);
+                self.data_store.create_lobby(lobby_params.region.clone(), lobby_id, lobby.clone());
+                self.data_store.create_region_lobby(lobby_id, lobby_params.region.clone());
                 self.data_store.edit_player(player_id, None, Some(lobby_id.clone()));
                 // self.data_store.print_global_lobby_map(); // Uncomment for debugging
                 let event = ServerEvent::LobbyCreated(lobby);
@@ -264,16 +514,25 @@ impl Websocket {
             },
             None => { // Player can join lobby if it exists and there is space
                 let region = self.find_region_lobby(lobby_id)?;
-                let mut lobby = self.find_lobby(region, lobby_id)?;
+                let mut lobby = self.find_lobby(region.clone(), lobby_id)?;
                 if lobby.player_list.len() == self.data_store.lobby_size() { // Check if lobby if full
                     return Err(GameSyncError::LobbyFullError)
                 }
+                if lobby.status != LobbyStatus::Idle { // Partial lobbies can be queueing
+                    return Err(GameSyncError::LobbyQueueError)
+                }
+                if self.data_store.block_lobby_joins() && self.data_store.is_blocked(lobby.leader, player_id) {
+                    return Err(GameSyncError::LobbyBlockedError)
+                }
                 lobby.player_list.push(player_id);
                 self.data_store.edit_lobby(region, lobby_id, lobby.clone())?;
                 self.data_store.edit_player(player_id, None, Some(lobby_id.clone()));
+                let summary = self.player_summary(player_id);
                 for player_id_lobby in lobby.player_list.iter() { // Send join notification to all players in lobby
-                    self.send_to_client(&player_id_lobby.to_string(), ServerEvent::LobbyJoined(player_id, lobby_id))?;
+                    self.send_to_client(&player_id_lobby.to_string(), ServerEvent::LobbyJoined(summary.clone(), lobby_id))?;
                 }
+                self.send_to_client(&player_id.to_string(), ServerEvent::LobbyInfo(lobby.clone(), self.lobby_roster(&lobby)))?;
+                self.send_to_client(&player_id.to_string(), ServerEvent::LobbyChatHistory(lobby_id, self.data_store.get_lobby_chat(lobby_id)))?;
             }
         }
         Ok(())
@@ -281,7 +540,7 @@ impl Websocket {
 
     pub fn delete_lobby(&mut self, player_id: PlayerID, lobby_id: LobbyID) -> Result<(), GameSyncError> {
         let region = self.find_region_lobby(lobby_id)?;
-        let lobby = self.find_lobby(region, lobby_id)?;
+        let lobby = self.find_lobby(region.clone(), lobby_id)?;
         if player_id != lobby.leader {
             return Err(GameSyncError::LobbyOwnerError)
         }
@@ -300,12 +559,22 @@ impl Websocket {
     }
 
     pub fn leave_lobby(&mut self, player_id: PlayerID, lobby_id: LobbyID) -> Result<(), GameSyncError> {
+        let abandoned = self.get_abandoned_match(lobby_id); // Looked up before a team is broken up
+        let lobby_id = self.leave_team(player_id, lobby_id)?; // Players in a team leave the lobby they queued with
         let region = self.find_region_lobby(lobby_id)?;
-        let mut lobby = self.find_lobby(region, lobby_id)?;
+        let mut lobby = self.find_lobby(region.clone(), lobby_id)?;
         // If leader leaves, delete lobby and send message to all players in lobby
         // Leader will be the last one to leave. If it isn't, lobby will be deleted regardless
         match lobby.player_list.iter().find(|&&p| p == player_id) { // If player is part of this lobby
             Some(_) => {
+                if lobby.status == LobbyStatus::ReadyCheck { // Leaving during a ready check declines the match
+                    self.decline_lobby_match(player_id, lobby_id)?;
+                    lobby = self.find_lobby(region.clone(), lobby_id)?;
+                }
+                if let Some(game) = abandoned {
+                    self.penalize(player_id, Offense::Abandon, game.mode)?;
+                    self.data_store.remove_match_player(game.match_id, player_id); // Not rated with the result
+                }
                 // Regardless of whether player is leader or part of lobby, if lobby is in queue, it will be removed
                 // If lobby is queueing, remove from queue and make idle.
                 let mut lobby_queueing: bool = false;
@@ -313,12 +582,13 @@ impl Websocket {
                     lobby.status = LobbyStatus::Idle;
                     match lobby.params.mode {
                         GameMode::Casual => {
-                            self.data_store.remove_casual_lobby(region, lobby.lobby_id);
+                            self.data_store.remove_casual_lobby(region.clone(), lobby.lobby_id);
                         },
                         GameMode::Competitive => {
-                            self.data_store.remove_competitive_lobby(region, lobby.lobby_id);
+                            self.data_store.remove_competitive_lobby(region.clone(), lobby.lobby_id);
                         }
                     }
+                    self.data_store.remove_party_lobby(region.clone(), lobby.params.mode, lobby.lobby_id);
                     lobby_queueing = true;
                 }
 
@@ -343,6 +613,7 @@ impl Websocket {
                         }
                         self.send_to_client(&player_id_lobby.to_string(), ServerEvent::LobbyLeft(player_id, lobby_id))?; // Notify that this particular player has left
                     }
+                    self.open_backfill(&lobby)?;
                 }
             },
             None => return Err(GameSyncError::LobbyInviteError)
@@ -361,8 +632,12 @@ impl Websocket {
                 else {
                     let region = self.find_region_lobby(lobby_id)?;
                     let lobby = self.find_lobby(region, lobby_id)?;
+                    self.check_lobby_mute(player_id, lobby_id)?;
+                    let message = self.filter_message(player_id, MessageKind::Lobby(lobby_id), message)?;
+                    let summary = self.player_summary(player_id);
+                    self.data_store.add_lobby_message(lobby_id, summary.clone(), message.clone());
                     for player_id_lobby in lobby.player_list.iter() { // Send message to all players in lobby
-                        self.send_to_client(&player_id_lobby.to_string(), ServerEvent::LobbyMessage(player_id, message.clone()))?; // Can leave as clone for now. Optionally figure out better way
+                        self.send_to_client(&player_id_lobby.to_string(), ServerEvent::LobbyMessage(summary.clone(), message.clone()))?; // Can leave as clone for now. Optionally figure out better way
                     }
                 }
             },
@@ -378,6 +653,9 @@ impl Websocket {
         match player_lobby {
             Some(player_lobby) => {
                 if player_lobby == lobby_id {
+                    if self.data_store.is_blocked(invitee_id, player_id) { // Dropped without telling the sender
+                        return Ok(())
+                    }
                     self.send_to_client(&invitee_id.to_string(), ServerEvent::LobbyInvited(lobby_id))?;
                 }
                 else {
@@ -390,51 +668,46 @@ impl Websocket {
     }
 
     pub fn get_public_lobbies(&mut self, player_id: PlayerID, region: Region) -> Result<(), GameSyncError> {
+        if !self.data_store.is_valid_region(&region) {
+            return Err(GameSyncError::RegionError);
+        }
         let public_lobbies = self.data_store.get_region_lobbies(region);
         let event = ServerEvent::PublicLobbies(public_lobbies);
         self.send_to_client(&player_id.to_string(), event)?;
         Ok(())
     }
 
-    pub fn edit_player(&mut self, player: Player) -> Result<(), GameSyncError> {
-        let player_info = self.find_player(player.player_id)?;
-        match player_info.1 {
-            Some(lobby_id) => { // Checks if player is already in a lobby
-                let region = self.find_region_lobby(lobby_id)?;
-                let lobby = self.find_lobby(region, lobby_id)?;
-                if lobby.status != LobbyStatus::Idle {
-                    return Err(GameSyncError::PlayerEditError);
-                }
-            },
-            None => { // Player can be edited if not in lobby
-            }
-        }
-        self.data_store.edit_player(player.player_id, Some(player), None);
-        let event = ServerEvent::PlayerEdited(player.player_id);
-        self.send_to_client(&player.player_id.to_string(), event)?;
-        Ok(())
-    }
-
     pub fn clean_up_player(&mut self, endpoint: Endpoint) -> Result<(), GameSyncError> {
         let player_id = self.data_store.get_user(endpoint);
         if let Some(player_id) = player_id {
             let player_info = self.find_player(player_id)?;
             match player_info.1 {
                 Some(player_lobby) => { // If user is part of a lobby, need to delete if owner, leave if in party
+                    let abandoned = self.get_abandoned_match(player_lobby); // Looked up before a team is broken up
+                    let player_lobby = self.leave_team(player_id, player_lobby)?;
                     // If the lobby was queuing then remove from queues and message players
                     let region = self.find_region_lobby(player_lobby)?;
-                    let mut lobby = self.find_lobby(region, player_lobby)?;
+                    let mut lobby = self.find_lobby(region.clone(), player_lobby)?;
+                    if lobby.status == LobbyStatus::ReadyCheck { // Disconnecting during a ready check declines the match
+                        self.decline_lobby_match(player_id, player_lobby)?;
+                        lobby = self.find_lobby(region, player_lobby)?;
+                    }
+                    if let Some(game) = abandoned { // Disconnecting mid-match abandons it
+                        self.penalize(player_id, Offense::Abandon, game.mode)?;
+                        self.data_store.remove_match_player(game.match_id, player_id); // Not rated with the result
+                    }
                     let mut lobby_queueing: bool = false;
                     if lobby.status == LobbyStatus::Queueing {
                         lobby.status = LobbyStatus::Idle;
                         match lobby.params.mode {
                             GameMode::Casual => {
-                                self.data_store.remove_casual_lobby(lobby.params.region, lobby.lobby_id);
+                                self.data_store.remove_casual_lobby(lobby.params.region.clone(), lobby.lobby_id);
                             },
                             GameMode::Competitive => {
-                                self.data_store.remove_competitive_lobby(lobby.params.region, lobby.lobby_id);
+                                self.data_store.remove_competitive_lobby(lobby.params.region.clone(), lobby.lobby_id);
                             }
                         }
+                        self.data_store.remove_party_lobby(lobby.params.region.clone(), lobby.params.mode, lobby.lobby_id);
                         lobby_queueing = true;
                     }
                     if player_id == lobby.leader { // If user is leader of a lobby, delete and kick party
@@ -451,7 +724,7 @@ impl Websocket {
                     }
                     else { // If user is part of a lobby (i.e. not a leader)
                         lobby.player_list.retain(|&player| player != player_id); // Remove player
-                        self.data_store.edit_lobby(lobby.params.region, lobby.lobby_id, lobby.clone())?; // Edit lobby
+                        self.data_store.edit_lobby(lobby.params.region.clone(), lobby.lobby_id, lobby.clone())?; // Edit lobby
                         self.data_store.remove_player_lobby(player_id); // Remove the leaving user
                         for player_id_lobby in lobby.player_list.iter() { // Notify all remaining players in lobby
                             if lobby_queueing {
@@ -460,6 +733,7 @@ impl Websocket {
                             self.send_to_client(&player_id_lobby.to_string(), ServerEvent::LobbyLeft(player_id, lobby.lobby_id))?; // Notify that this particular player has left
                         }
                         self.data_store.delete_player(player_id); // Delete player at the end
+                        self.open_backfill(&lobby)?;
                     }
                 },
                 None => { // If user not part of a lobby, can just remove from player map and return. Other data structures should ideally not have this player in them
@@ -475,39 +749,55 @@ impl Websocket {
 
     pub fn queue_lobby(&mut self, player_id: PlayerID, lobby_id: LobbyID) -> Result<(), GameSyncError> {
         let region = self.find_region_lobby(lobby_id)?;
-        let mut lobby = self.find_lobby(region, lobby_id)?;
+        let mut lobby = self.find_lobby(region.clone(), lobby_id)?;
         if player_id != lobby.leader {
             return Err(GameSyncError::LobbyOwnerError)
         }
         if lobby.status != LobbyStatus::Idle {
             return Err(GameSyncError::LobbyQueueError)
         }
-        if lobby.player_list.len() != self.data_store.lobby_size() {
+        if lobby.player_list.len() > self.data_store.lobby_size() {
             return Err(GameSyncError::LobbySizeError)
         }
+        if let Some(until) = lobby.player_list.iter().filter_map(|player| self.data_store.get_queue_penalty(*player)).max() {
+            return Err(GameSyncError::QueueBanned { until })
+        }
+        if self.get_role_assignment(&lobby.player_list).is_none() {
+            return Err(GameSyncError::RoleCompositionError)
+        }
 
         lobby.status = LobbyStatus::Queueing;
-
-        match lobby.params.mode {
-            GameMode::Casual => {
-                self.data_store.add_casual_lobby(region, lobby.clone());
-            },
-            GameMode::Competitive => {
-                self.data_store.add_competitive_lobby(region, lobby.clone());
+        self.data_store.set_queue_time(lobby_id, SystemTime::now());
+        let partial = lobby.player_list.len() < self.data_store.lobby_size();
+
+        if partial { // Partial lobbies are assembled into full teams before matchmaking
+            self.data_store.add_party_lobby(region.clone(), lobby.params.mode, lobby_id);
+        } else {
+            match lobby.params.mode {
+                GameMode::Casual => {
+                    self.data_store.add_casual_lobby(region.clone(), lobby.clone());
+                },
+                GameMode::Competitive => {
+                    self.data_store.add_competitive_lobby(region.clone(), lobby.clone());
+                }
             }
         }
 
         for player_id_lobby in lobby.player_list.iter() { // Edit and Message all players in lobby
-            self.data_store.edit_lobby(region, lobby_id, lobby.clone())?;
+            self.data_store.edit_lobby(region.clone(), lobby_id, lobby.clone())?;
             self.data_store.edit_player(player_id_lobby.clone(), None, Some(lobby_id.clone()));
             self.send_to_client(&player_id_lobby.to_string(), ServerEvent::LobbyQueued(lobby_id))?;
         }
+        if partial && !self.fill_backfill(region.clone(), lobby_id)? { // Solo players join a match in progress before forming a team
+            self.form_team(region, lobby_id)?;
+        }
         Ok(())
     }
 
     pub fn check_match(&mut self, player_id: PlayerID, lobby_id: LobbyID, threshold: usize) -> Result<(), GameSyncError> {
+        let lobby_id = self.data_store.get_party_team(lobby_id).unwrap_or(lobby_id); // Teamed lobbies queue as their team
         let region = self.find_region_lobby(lobby_id)?;
-        let mut lobby = self.find_lobby(region, lobby_id)?;
+        let mut lobby = self.find_lobby(region.clone(), lobby_id)?;
         if player_id != lobby.leader { // Only let leader check to avoid multiple map operations
             return Err(GameSyncError::LobbyOwnerError)
         }
@@ -517,26 +807,39 @@ impl Websocket {
 
         // self.data_store.print_casual_lobbies();
         lobby.queue_threshold = threshold;
-        self.data_store.edit_lobby(region, lobby_id, lobby.clone())?;
+        self.data_store.edit_lobby(region.clone(), lobby_id, lobby.clone())?;
+
+        if lobby.player_list.len() < self.data_store.lobby_size() { // Partial lobby. Look for teammates first
+            if self.fill_backfill(region.clone(), lobby_id)? {
+                return Ok(())
+            }
+            return match self.form_team(region.clone(), lobby_id)? {
+                Some(team_id) => { // Check straight away on behalf of the team leader
+                    let team = self.find_lobby(region, team_id)?;
+                    self.check_match(team.leader, team_id, threshold)
+                },
+                None => self.send_to_client(&player_id.to_string(), ServerEvent::MatchNotFound)
+            }
+        }
 
         match lobby.params.mode {
             GameMode::Casual => {
-                match self.data_store.check_casual_lobby(region, lobby_id) {
+                match self.data_store.check_casual_lobby(region.clone(), lobby_id) {
                     Some(lobbies) => {
                         self.finalize_match(region, lobbies, threshold)?;
                     },
-                    None => {
-                        self.send_to_client(&player_id.to_string(), ServerEvent::MatchNotFound)?;
+                    None => { // Try other regions the lobby accepts once it has waited long enough
+                        self.check_fallback_match(player_id, &lobby, threshold)?;
                     }
                 }
             },
             GameMode::Competitive => {
-                match self.data_store.check_competitive_lobby(region, lobby_id, threshold) {
+                match self.data_store.check_competitive_lobby(region.clone(), lobby_id, threshold) {
                     Some(lobbies) => {
                         self.finalize_match(region, lobbies, threshold)?;
                     },
-                    None => {
-                        self.send_to_client(&player_id.to_string(), ServerEvent::MatchNotFound)?;
+                    None => { // Try other regions the lobby accepts once it has waited long enough
+                        self.check_fallback_match(player_id, &lobby, threshold)?;
                     }
                 }
             }
@@ -544,26 +847,64 @@ impl Websocket {
         Ok(())
     }
 
-    fn finalize_match(&mut self, region: Region, lobbies: (Lobby, Lobby), threshold: usize) -> Result<(), GameSyncError> {
-        let (mut lobby1, mut lobby2) = lobbies;
-        println!("Match found between lobby {} and {}", lobby1.lobby_id, lobby2.lobby_id);
-        lobby1.status = LobbyStatus::Ingame;
+    pub(crate) fn finalize_match(&mut self, region: Region, lobbies: (Lobby, Lobby), threshold: usize) -> Result<(), GameSyncError> {
+        let (mut lobby1, lobby2) = lobbies;
+        let match_id = Uuid::new_v4();
+        println!("Match {} found between lobby {} and {}", match_id, lobby1.lobby_id, lobby2.lobby_id);
         lobby1.queue_threshold = threshold;
-        for player_id_lobby in lobby1.player_list.iter() { // Edit and Message all players in lobby
-            self.data_store.edit_lobby(region, lobby1.lobby_id, lobby1.clone())?;
-            self.send_to_client(&player_id_lobby.to_string(), ServerEvent::MatchFound(lobby2.clone()))?; // Opponent lobby
+        let game = Match {
+            match_id,
+            region,
+            mode: lobby1.params.mode,
+            lobbies: (lobby1, lobby2),
+            reports: Vec::new(),
+            deadline: None,
+            accepted: Vec::new(),
+            left: Vec::new()
+        };
+        match self.data_store.ready_check() {
+            Some(timeout) => self.propose_match(game, timeout), // Players must accept before going in game
+            None => self.start_match(game)
         }
+    }
+
+    pub(crate) fn start_match(&mut self, mut game: Match) -> Result<(), GameSyncError> {
+        let (region, match_id) = (game.region.clone(), game.match_id);
+        let (mut lobby1, mut lobby2) = game.lobbies.clone();
+        lobby1.status = LobbyStatus::Ingame;
         lobby2.status = LobbyStatus::Ingame;
+        game.lobbies = (lobby1.clone(), lobby2.clone());
+        game.deadline = None;
+        self.data_store.add_match(game);
+        if let Some(expiry) = self.data_store.match_expiry() {
+            self.handler.signals().send_with_timer(ServerSignal::MatchExpired(match_id), expiry);
+        }
+        for lobby in [&lobby1, &lobby2] { // Record how long each lobby waited for this match
+            if let Some(queued_at) = self.data_store.get_queue_time(lobby.lobby_id) {
+                let wait = SystemTime::now().duration_since(queued_at).unwrap_or_default();
+                self.data_store.add_wait_time(lobby.params.region.clone(), lobby.params.mode, wait);
+                self.data_store.remove_queue_time(lobby.lobby_id);
+            }
+        }
+        let roles1 = self.get_role_assignment(&lobby1.player_list).unwrap_or_default();
+        let roles2 = self.get_role_assignment(&lobby2.player_list).unwrap_or_default();
+        for player_id_lobby in lobby1.player_list.iter() { // Edit and Message all players in lobby
+            self.data_store.edit_lobby(lobby1.params.region.clone(), lobby1.lobby_id, lobby1.clone())?;
+            let role = roles1.get(player_id_lobby).cloned();
+            self.send_to_client(&player_id_lobby.to_string(), ServerEvent::MatchFound(MatchInfo { match_id, region: region.clone(), opponent: lobby2.clone(), role }))?; // Opponent lobby
+        }
         for player_id_lobby in lobby2.player_list.iter() { // Edit and Message all players in lobby
-            self.data_store.edit_lobby(region, lobby2.lobby_id, lobby2.clone())?;
-            self.send_to_client(&player_id_lobby.to_string(), ServerEvent::MatchFound(lobby1.clone()))?; // Opponent lobby
+            self.data_store.edit_lobby(lobby2.params.region.clone(), lobby2.lobby_id, lobby2.clone())?;
+            let role = roles2.get(player_id_lobby).cloned();
+            self.send_to_client(&player_id_lobby.to_string(), ServerEvent::MatchFound(MatchInfo { match_id, region: region.clone(), opponent: lobby1.clone(), role }))?; // Opponent lobby
         }
         Ok(())
     }
 
     pub fn stop_queue(&mut self, player_id: PlayerID, lobby_id: LobbyID) -> Result<(), GameSyncError> {
+        let lobby_id = self.data_store.get_party_team(lobby_id).unwrap_or(lobby_id); // Only the team leader can stop a team's queue
         let region = self.find_region_lobby(lobby_id)?;
-        let mut lobby = self.find_lobby(region, lobby_id)?;
+        let mut lobby = self.find_lobby(region.clone(), lobby_id)?;
         if player_id != lobby.leader {
             return Err(GameSyncError::LobbyOwnerError)
         }
@@ -575,23 +916,28 @@ impl Websocket {
 
         match lobby.params.mode {
             GameMode::Casual => {
-                self.data_store.remove_casual_lobby(region, lobby_id);
+                self.data_store.remove_casual_lobby(region.clone(), lobby_id);
             },
             GameMode::Competitive => {
-                self.data_store.remove_competitive_lobby(region, lobby_id);
+                self.data_store.remove_competitive_lobby(region.clone(), lobby_id);
             }
         }
+        self.data_store.remove_party_lobby(region.clone(), lobby.params.mode, lobby_id);
 
         for player_id_lobby in lobby.player_list.iter() { // Edit and Message all players in lobby
-            self.data_store.edit_lobby(region, lobby_id, lobby.clone())?;
+            self.data_store.edit_lobby(region.clone(), lobby_id, lobby.clone())?;
             self.send_to_client(&player_id_lobby.to_string(), ServerEvent::QueueStopped(lobby_id))?;
         }
+        if !lobby.parties.is_empty() {
+            self.dissolve_team(lobby_id, LobbyStatus::Idle)?;
+        }
         Ok(())
     }
 
     pub fn leave_game_as_lobby(&mut self, player_id: PlayerID, lobby_id: LobbyID) -> Result<(), GameSyncError> {
+        let lobby_id = self.data_store.get_party_team(lobby_id).unwrap_or(lobby_id);
         let region = self.find_region_lobby(lobby_id)?;
-        let mut lobby = self.find_lobby(region, lobby_id)?;
+        let mut lobby = self.find_lobby(region.clone(), lobby_id)?;
         if player_id != lobby.leader { // Only lobby leader is allowed to leave game for the entire lobby
             return Err(GameSyncError::LobbyOwnerError)
         }
@@ -599,12 +945,25 @@ impl Websocket {
             return Err(GameSyncError::LeaveGameError)
         }
 
+        if let Some(game) = self.data_store.get_lobby_match(lobby_id).filter(|game| game.deadline.is_none()) {
+            let reported = game.reports.iter().any(|(reporter, _)| *reporter == lobby_id);
+            if game.mode == GameMode::Competitive && !reported { // Leaving before reporting abandons the match. Casual matches have no result to wait for
+                for player_id_lobby in lobby.player_list.iter() {
+                    self.penalize(*player_id_lobby, Offense::Abandon, game.mode)?;
+                }
+            }
+            self.data_store.leave_match(game.match_id, lobby_id); // Stays open for the other lobby's report
+        }
         lobby.status = LobbyStatus::Idle;
+        self.data_store.remove_backfill_lobby(region.clone(), lobby.params.mode, lobby_id);
 
         for player_id_lobby in lobby.player_list.iter() { // Edit and Message all players in lobby
-            self.data_store.edit_lobby(region, lobby_id, lobby.clone())?;
+            self.data_store.edit_lobby(region.clone(), lobby_id, lobby.clone())?;
             self.send_to_client(&player_id_lobby.to_string(), ServerEvent::LeftGame(lobby_id))?;
         }
+        if !lobby.parties.is_empty() { // Team lobbies only last for one match
+            self.dissolve_team(lobby_id, LobbyStatus::Idle)?;
+        }
         // self.data_store.print_global_lobby_map(); // Uncomment for debugging
         Ok(())
     }
@@ -612,14 +971,14 @@ impl Websocket {
     pub fn get_lobby_info (&mut self, player_id: PlayerID, lobby_id: LobbyID) -> Result<(), GameSyncError> {
         let region = self.find_region_lobby(lobby_id)?;
         let lobby = self.find_lobby(region, lobby_id)?;
-        let event = ServerEvent::LobbyInfo(lobby);
+        let event = ServerEvent::LobbyInfo(lobby.clone(), self.lobby_roster(&lobby));
         self.send_to_client(&player_id.to_string(), event)?;
         Ok(())
     }
 
     /*  HELPER FUNCTIONS */
 
-    fn find_region_lobby(&mut self, lobby_id: LobbyID) -> Result<Region, GameSyncError> {
+    pub(crate) fn find_region_lobby(&mut self, lobby_id: LobbyID) -> Result<Region, GameSyncError> {
         match self.data_store.get_region_lobby(&lobby_id) {
             Some(region) => {
                 Ok(region)
@@ -628,7 +987,7 @@ impl Websocket {
         }
     }
 
-    fn find_lobby(&mut self, region: Region, lobby_id: LobbyID) -> Result<Lobby, GameSyncError> {
+    pub(crate) fn find_lobby(&mut self, region: Region, lobby_id: LobbyID) -> Result<Lobby, GameSyncError> {
         match self.data_store.get_lobby(region, lobby_id) {
             Some(lobby) => {
                 Ok(lobby)
@@ -637,7 +996,7 @@ impl Websocket {
         }
     }
 
-    fn find_player(&mut self, player_id: PlayerID) -> Result<(Player, Option<LobbyID>), GameSyncError> {
+    pub(crate) fn find_player(&mut self, player_id: PlayerID) -> Result<(Player, Option<LobbyID>), GameSyncError> {
         match self.data_store.get_player_info(player_id) {
             Some(player_info) => {
                 Ok(player_info)
@@ -646,4 +1005,11 @@ impl Websocket {
         }
     }
 
+    pub(crate) fn find_match(&mut self, match_id: MatchID) -> Result<Match, GameSyncError> {
+        match self.data_store.get_match(match_id) {
+            Some(game) => Ok(game),
+            None => Err(GameSyncError::MatchFindError)
+        }
+    }
+
 }
@@ -0,0 +1,102 @@
+use std::time::{Duration, SystemTime};
+use crate::error::{GameSyncError, print_error};
+use crate::lobby::{*};
+use crate::networking::{ServerEvent, Websocket};
+use crate::rating::Glicko2Rating;
+
+#[derive(Debug, Clone)]
+pub struct PenaltyParams {
+    pub dodge_lockouts: Vec<Duration>, // Queue lockout for the 1st, 2nd, ... dodge. The last entry repeats. Empty disables lockouts
+    pub abandon_lockouts: Vec<Duration>, // Same for abandons
+    pub dodge_rating_loss: usize, // Rating lost per dodge of a competitive match
+    pub abandon_rating_loss: usize, // Rating lost per abandoned competitive match
+    pub forgive_after: Option<Duration>, // Offense counts are reset after this long without an offense. None never resets them
+}
+
+impl Default for PenaltyParams {
+    fn default() -> Self {
+        PenaltyParams {
+            dodge_lockouts: Vec::new(),
+            abandon_lockouts: Vec::new(),
+            dodge_rating_loss: 0,
+            abandon_rating_loss: 0,
+            forgive_after: Some(Duration::from_secs(24 * 60 * 60)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Offense {
+    Dodge, // Declining or missing the ready check of a proposed match
+    Abandon, // Leaving an in-game lobby or disconnecting before the match result is reported
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct PenaltyRecord {
+    pub dodges: usize,
+    pub abandons: usize,
+    pub last_offense: Option<SystemTime>,
+}
+
+impl Websocket {
+    // Records the offense and applies the lockout and rating loss for the player's offense count
+    pub(crate) fn penalize(&mut self, player_id: PlayerID, offense: Offense, mode: GameMode) -> Result<(), GameSyncError> {
+        let params = self.data_store.penalty_params();
+        let now = SystemTime::now();
+        let mut record = self.data_store.get_penalty_record(player_id).unwrap_or_default();
+        let forgiven = match (params.forgive_after, record.last_offense) {
+            (Some(forgive_after), Some(last_offense)) => now.duration_since(last_offense).is_ok_and(|since| since >= forgive_after),
+            _ => false
+        };
+        if forgiven {
+            record = PenaltyRecord::default();
+        }
+        let (count, lockouts, rating_loss) = match offense {
+            Offense::Dodge => {
+                record.dodges += 1;
+                (record.dodges, &params.dodge_lockouts, params.dodge_rating_loss)
+            },
+            Offense::Abandon => {
+                record.abandons += 1;
+                (record.abandons, &params.abandon_lockouts, params.abandon_rating_loss)
+            }
+        };
+        record.last_offense = Some(now);
+        self.data_store.set_penalty_record(player_id, record.clone());
+        println!("Player {} {:?} #{}", player_id, offense, count);
+
+        if let Some(lockout) = lockouts.get(count - 1).or(lockouts.last()) {
+            let until = now + *lockout;
+            if self.data_store.get_queue_penalty(player_id).is_none_or(|current| current < until) {
+                self.data_store.add_queue_penalty(player_id, until);
+            }
+            if let Err(e) = self.send_to_client(&player_id.to_string(), ServerEvent::QueueBanned(until)) {
+                print_error(e); // Player may be disconnecting
+            }
+        }
+        if mode == GameMode::Competitive && rating_loss > 0 {
+            let (player, _) = self.find_player(player_id)?;
+            let rating = player.rating.saturating_sub(rating_loss);
+            self.data_store.set_player_rating(player_id, rating);
+            if let Some(glicko) = self.data_store.get_glicko_rating(player_id) {
+                self.data_store.set_glicko_rating(player_id, Glicko2Rating { rating: rating as f64, ..glicko });
+            }
+            if let Err(e) = self.send_to_client(&player_id.to_string(), ServerEvent::RatingChanged(player.rating, rating)) {
+                print_error(e);
+            }
+        }
+        Ok(())
+    }
+
+    // Started match the players of a lobby would abandon by leaving it now. Parties in a team play the team's match.
+    // A lobby that already reported the result or left the game can no longer abandon it
+    pub(crate) fn get_abandoned_match(&self, lobby_id: LobbyID) -> Option<Match> {
+        let lobby_id = self.data_store.get_party_team(lobby_id).unwrap_or(lobby_id);
+        let region = self.data_store.get_region_lobby(&lobby_id)?;
+        if self.data_store.get_lobby(region, lobby_id)?.status != LobbyStatus::Ingame {
+            return None
+        }
+        self.data_store.get_lobby_match(lobby_id)
+            .filter(|game| game.deadline.is_none() && !game.reports.iter().any(|(reporter, _)| *reporter == lobby_id))
+    }
+}
@@ -0,0 +1,85 @@
+use crate::error::GameSyncError;
+use crate::lobby::{*};
+use crate::networking::{ServerEvent, Websocket};
+
+const MAX_PROFILES_PER_REQUEST: usize = 100; // Most profiles returned by a single GetProfiles
+
+#[derive(Debug, Clone)]
+pub struct ProfileRules {
+    pub min_name_length: usize, // In characters. An empty display name is always allowed and means unset
+    pub max_name_length: usize,
+    pub unique_names: bool, // Display names are compared case-insensitively. Accounts keep their name while offline
+    pub max_metadata_entries: usize,
+    pub max_metadata_length: usize, // Longest key or value, in characters
+}
+
+impl Default for ProfileRules {
+    fn default() -> Self {
+        ProfileRules {
+            min_name_length: 3,
+            max_name_length: 24,
+            unique_names: true,
+            max_metadata_entries: 16,
+            max_metadata_length: 256,
+        }
+    }
+}
+
+impl ProfileRules {
+    pub fn validate(&self, profile: &PlayerProfile) -> bool {
+        let name = &profile.display_name;
+        let name_length = name.chars().count();
+        let name_valid = name.is_empty() || (
+            (self.min_name_length..=self.max_name_length).contains(&name_length)
+            && name.trim() == name
+            && !name.chars().any(char::is_control)
+        );
+        let metadata_valid = profile.metadata.len() <= self.max_metadata_entries
+            && profile.metadata.iter().all(|(key, value)| {
+                key.chars().count() <= self.max_metadata_length && value.chars().count() <= self.max_metadata_length
+            });
+        let avatar_valid = profile.avatar_id.as_ref().is_none_or(|avatar_id| avatar_id.chars().count() <= self.max_metadata_length);
+        name_valid && metadata_valid && avatar_valid
+    }
+}
+
+impl Websocket {
+    pub fn edit_player(&mut self, player_id: PlayerID, profile: PlayerProfile) -> Result<(), GameSyncError> {
+        let rules = self.data_store.profile_rules();
+        if !rules.validate(&profile) {
+            return Err(GameSyncError::ProfileInvalidError)
+        }
+        self.find_player(player_id)?; // Player ID and rating are never taken from the client
+        if rules.unique_names && !self.data_store.reserve_display_name(player_id, &profile.display_name) {
+            return Err(GameSyncError::ProfileNameTakenError)
+        }
+        let display_name = profile.display_name.to_lowercase();
+        let old_profile = self.data_store.set_player_profile(player_id, profile).ok_or(GameSyncError::PlayerFindError)?;
+        if old_profile.display_name.to_lowercase() != display_name {
+            self.data_store.release_display_name(player_id, &old_profile.display_name);
+        }
+        let event = ServerEvent::PlayerEdited(player_id);
+        self.send_to_client(&player_id.to_string(), event)?;
+        Ok(())
+    }
+
+    // Name and avatar sent along with a player's ID in lobby and chat events
+    pub(crate) fn player_summary(&self, player_id: PlayerID) -> PlayerSummary {
+        let profile = self.data_store.get_player_profile(player_id).unwrap_or_default();
+        PlayerSummary { player_id, display_name: profile.display_name, avatar_id: profile.avatar_id }
+    }
+
+    pub(crate) fn lobby_roster(&self, lobby: &Lobby) -> Vec<PlayerSummary> {
+        lobby.player_list.iter().map(|player_id| self.player_summary(*player_id)).collect()
+    }
+
+    // Unknown players are left out
+    pub fn get_profiles(&mut self, player_id: PlayerID, player_ids: Vec<PlayerID>) -> Result<(), GameSyncError> {
+        let profiles = player_ids.into_iter()
+            .take(MAX_PROFILES_PER_REQUEST)
+            .filter_map(|target_id| Some((target_id, self.data_store.get_player_profile(target_id)?)))
+            .collect();
+        self.send_to_client(&player_id.to_string(), ServerEvent::Profiles(profiles))?;
+        Ok(())
+    }
+}
@@ -0,0 +1,142 @@
+use crate::error::{GameSyncError, print_error};
+use crate::lobby::{*};
+use crate::networking::{ServerEvent, Websocket};
+use crate::rating::Glicko2Rating;
+
+#[derive(Debug, Clone)]
+pub struct Tier {
+    pub name: String,
+    pub min_rating: usize, // Lowest rating in this tier
+    pub divisions: usize, // Equal rating bands the tier is split into. The highest tier always has a single division
+}
+
+impl Tier {
+    pub fn new(name: &str, min_rating: usize, divisions: usize) -> Self {
+        Tier { name: name.to_string(), min_rating, divisions }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RankedParams {
+    pub tiers: Vec<Tier>, // Ordered from lowest to highest min_rating
+    pub placement_matches: usize, // Competitive matches a player must finish each season before being ranked
+    pub soft_reset: f64, // Fraction of the distance to initial_rating removed at the end of a season. 0 keeps ratings, 1 resets everyone
+}
+
+impl Default for RankedParams {
+    fn default() -> Self {
+        RankedParams {
+            tiers: vec![
+                Tier::new("Bronze", 0, 3),
+                Tier::new("Silver", 900, 3),
+                Tier::new("Gold", 1100, 3),
+                Tier::new("Platinum", 1300, 3),
+                Tier::new("Diamond", 1500, 3),
+                Tier::new("Master", 1700, 1),
+            ],
+            placement_matches: 5,
+            soft_reset: 0.5,
+        }
+    }
+}
+
+// Ranked progress of a player in the current season
+#[derive(Debug, Clone, Default)]
+pub struct RankedRecord {
+    pub matches_played: usize,
+    pub history: Vec<SeasonResult>,
+}
+
+// Tier and division for a rating. Ratings below the lowest tier are placed at the bottom of it
+pub fn rank_for_rating(tiers: &[Tier], rating: usize) -> Option<Rank> {
+    let index = tiers.iter().rposition(|tier| tier.min_rating <= rating).unwrap_or(0);
+    let tier = tiers.get(index)?;
+    let Some(next) = tiers.get(index + 1) else {
+        return Some(Rank { tier: tier.name.clone(), division: 1, progress: None })
+    };
+    let divisions = tier.divisions.max(1);
+    let span = next.min_rating.saturating_sub(tier.min_rating).max(1);
+    let points = rating.saturating_sub(tier.min_rating).min(span - 1);
+    let band = (points * divisions / span).min(divisions - 1); // 0 is the lowest division
+    let band_start = span * band / divisions;
+    let band_end = span * (band + 1) / divisions;
+    let progress = (points - band_start) * 100 / band_end.saturating_sub(band_start).max(1);
+    Some(Rank { tier: tier.name.clone(), division: divisions - band, progress: Some(progress) })
+}
+
+impl Websocket {
+    // Offline accounts are ranked from the rating saved when they last disconnected
+    pub fn get_rank_info(&mut self, player_id: PlayerID) -> Result<RankInfo, GameSyncError> {
+        let player = self.data_store.get_player_info(player_id).map(|(player, _)| player)
+            .or_else(|| self.data_store.get_account_player(player_id))
+            .ok_or(GameSyncError::PlayerFindError)?;
+        let params = self.data_store.ranked_params();
+        let record = self.data_store.get_ranked_record(player_id).unwrap_or_default();
+        let placement_matches_left = params.placement_matches.saturating_sub(record.matches_played);
+        let rank = if placement_matches_left == 0 { rank_for_rating(&params.tiers, player.rating) } else { None };
+        Ok(RankInfo {
+            player_id,
+            season: self.data_store.season(),
+            rank,
+            placement_matches_left,
+            season_history: record.history,
+        })
+    }
+
+    pub fn send_rank_info(&mut self, player_id: PlayerID, target_id: PlayerID) -> Result<(), GameSyncError> {
+        let rank_info = self.get_rank_info(target_id)?;
+        self.send_to_client(&player_id.to_string(), ServerEvent::RankInfo(rank_info))?;
+        Ok(())
+    }
+
+    pub(crate) fn add_ranked_match(&mut self, player_id: PlayerID) {
+        let mut record = self.data_store.get_ranked_record(player_id).unwrap_or_default();
+        record.matches_played += 1;
+        self.data_store.set_ranked_record(player_id, record);
+    }
+
+    // Archives every player's final rank, soft resets ratings towards initial_rating and starts the next season.
+    // Offline accounts are reset too. Returns the new season number
+    pub fn end_season(&mut self) -> Result<usize, GameSyncError> {
+        let params = self.data_store.ranked_params();
+        let initial_rating = self.data_store.initial_rating() as f64;
+        let season = self.data_store.season();
+        let reset = params.soft_reset.clamp(0.0, 1.0);
+
+        let online = self.data_store.get_all_players().into_iter().map(|player| (player, true));
+        let offline = self.data_store.get_offline_account_players().into_iter().map(|player| (player, false));
+        let players: Vec<(Player, bool)> = online.chain(offline).collect();
+        for (player, online) in players {
+            let rank_info = self.get_rank_info(player.player_id)?;
+            let mut record = self.data_store.get_ranked_record(player.player_id).unwrap_or_default();
+            record.history.push(SeasonResult { season, rating: player.rating, rank: rank_info.rank, matches_played: record.matches_played });
+            record.matches_played = 0;
+            self.data_store.set_ranked_record(player.player_id, record);
+
+            let rating = player.rating as f64;
+            let new_rating = (rating - (rating - initial_rating) * reset).round().max(0.0) as usize;
+            if new_rating == player.rating {
+                continue;
+            }
+            self.data_store.set_player_rating(player.player_id, new_rating);
+            let glicko = self.data_store.get_glicko_rating(player.player_id).unwrap_or(Glicko2Rating::new(new_rating));
+            self.data_store.set_glicko_rating(player.player_id, Glicko2Rating { rating: new_rating as f64, ..glicko });
+            if !online { // Sees the new rating on their next login
+                continue;
+            }
+            if let Err(e) = self.send_to_client(&player.player_id.to_string(), ServerEvent::RatingChanged(player.rating, new_rating)) {
+                print_error(e);
+            }
+        }
+        self.data_store.sort_competitive_lobbies(); // Queued lobbies' ratings changed
+
+        let new_season = self.data_store.start_next_season();
+        println!("Season {} ended. Season {} started", season, new_season);
+        for player in self.data_store.get_all_players() {
+            if let Err(e) = self.send_to_client(&player.player_id.to_string(), ServerEvent::SeasonStarted(new_season)) {
+                print_error(e);
+            }
+        }
+        Ok(new_season)
+    }
+}
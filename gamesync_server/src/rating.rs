@@ -0,0 +1,265 @@
+use std::f64::consts::PI;
+use crate::error::{GameSyncError, print_error};
+use crate::lobby::{*};
+use crate::networking::{ServerEvent, Websocket};
+
+const GLICKO2_SCALE: f64 = 173.7178;
+const GLICKO2_BASE: f64 = 1500.0;
+const GLICKO2_EPSILON: f64 = 0.000001;
+
+#[derive(Debug, Clone, Copy)]
+pub enum RatingModel {
+    Elo { k_factor: f64 },
+    Glicko2 { tau: f64 }, // System constant constraining volatility changes. Usually between 0.3 and 1.2
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Glicko2Rating {
+    pub rating: f64,
+    pub deviation: f64,
+    pub volatility: f64,
+}
+
+impl Glicko2Rating {
+    pub fn new(rating: usize) -> Self {
+        Glicko2Rating { rating: rating as f64, deviation: 350.0, volatility: 0.06 }
+    }
+}
+
+pub fn elo_update(rating: f64, opponent_rating: f64, score: f64, k_factor: f64) -> f64 {
+    let expected = 1.0 / (1.0 + 10f64.powf((opponent_rating - rating) / 400.0));
+    rating + k_factor * (score - expected)
+}
+
+// Single rating period update against one (composite) opponent, as described in Glickman's Glicko-2 paper
+pub fn glicko2_update(player: Glicko2Rating, opponent: Glicko2Rating, score: f64, tau: f64) -> Glicko2Rating {
+    let mu = (player.rating - GLICKO2_BASE) / GLICKO2_SCALE;
+    let phi = player.deviation / GLICKO2_SCALE;
+    let mu_j = (opponent.rating - GLICKO2_BASE) / GLICKO2_SCALE;
+    let phi_j = opponent.deviation / GLICKO2_SCALE;
+
+    let g = 1.0 / (1.0 + 3.0 * phi_j.powi(2) / PI.powi(2)).sqrt();
+    let expected = 1.0 / (1.0 + (-g * (mu - mu_j)).exp());
+    let v = 1.0 / (g.powi(2) * expected * (1.0 - expected));
+    let delta = v * g * (score - expected);
+
+    // Find the new volatility using the Illinois algorithm
+    let a = player.volatility.powi(2).ln();
+    let f = |x: f64| {
+        let ex = x.exp();
+        ex * (delta.powi(2) - phi.powi(2) - v - ex) / (2.0 * (phi.powi(2) + v + ex).powi(2)) - (x - a) / tau.powi(2)
+    };
+    let mut big_a = a;
+    let mut big_b = if delta.powi(2) > phi.powi(2) + v {
+        (delta.powi(2) - phi.powi(2) - v).ln()
+    } else {
+        let mut k = 1.0;
+        while f(a - k * tau) < 0.0 {
+            k += 1.0;
+        }
+        a - k * tau
+    };
+    let mut f_a = f(big_a);
+    let mut f_b = f(big_b);
+    while (big_b - big_a).abs() > GLICKO2_EPSILON {
+        let big_c = big_a + (big_a - big_b) * f_a / (f_b - f_a);
+        let f_c = f(big_c);
+        if f_c * f_b <= 0.0 {
+            big_a = big_b;
+            f_a = f_b;
+        } else {
+            f_a /= 2.0;
+        }
+        big_b = big_c;
+        f_b = f_c;
+    }
+    let volatility = (big_a / 2.0).exp();
+
+    let phi_star = (phi.powi(2) + volatility.powi(2)).sqrt();
+    let new_phi = 1.0 / (1.0 / phi_star.powi(2) + 1.0 / v).sqrt();
+    let new_mu = mu + new_phi.powi(2) * g * (score - expected);
+
+    Glicko2Rating {
+        rating: GLICKO2_SCALE * new_mu + GLICKO2_BASE,
+        deviation: GLICKO2_SCALE * new_phi,
+        volatility,
+    }
+}
+
+impl Websocket {
+    pub fn report_match_result(&mut self, player_id: PlayerID, match_id: MatchID, outcome: MatchOutcome) -> Result<(), GameSyncError> {
+        let game = self.find_match(match_id)?;
+        if game.deadline.is_some() { // Match has not started yet
+            return Err(GameSyncError::MatchReportError)
+        }
+        let lobby_id = if player_id == game.lobbies.0.leader {
+            game.lobbies.0.lobby_id
+        } else if player_id == game.lobbies.1.leader {
+            game.lobbies.1.lobby_id
+        } else { // Only the leaders of the lobbies in this match can report
+            return Err(GameSyncError::MatchReportError)
+        };
+
+        // A leader can correct their own report. Updated in place so a concurrent report is not lost
+        let game = self.data_store.add_match_report(match_id, lobby_id, outcome).ok_or(GameSyncError::MatchFindError)?;
+        if game.reports.len() < 2 {
+            return Ok(())
+        }
+        let (lobby1, lobby2) = game.lobbies.clone();
+
+        // Both sides have reported. Reconcile from lobby1's perspective
+        let outcome1 = game.reports.iter().find(|(reporter, _)| *reporter == lobby1.lobby_id).map(|(_, outcome)| *outcome);
+        let outcome2 = game.reports.iter().find(|(reporter, _)| *reporter == lobby2.lobby_id).map(|(_, outcome)| *outcome);
+        match (outcome1, outcome2) {
+            (Some(outcome1), Some(outcome2)) if outcome1 == outcome2.opposite() => {
+                self.resolve_match(game, outcome1)?;
+            },
+            _ => {
+                println!("Match {} results disputed", match_id);
+                for player_id_match in lobby1.player_list.iter().chain(lobby2.player_list.iter()) {
+                    self.send_to_client(&player_id_match.to_string(), ServerEvent::MatchResultDisputed(match_id))?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Started matches nobody reported are dropped without changing any ratings
+    pub(crate) fn expire_match(&mut self, match_id: MatchID) {
+        if self.data_store.remove_match(match_id).is_some() {
+            println!("Match {} expired without a result", match_id);
+        }
+    }
+
+    pub fn set_rating(&mut self, player_id: PlayerID, rating: usize) -> Result<(), GameSyncError> {
+        let player_info = self.find_player(player_id)?;
+        if let Some(lobby_id) = player_info.1 { // Competitive queue is ordered by rating, so only edit idle players
+            let region = self.find_region_lobby(lobby_id)?;
+            let lobby = self.find_lobby(region, lobby_id)?;
+            if lobby.status != LobbyStatus::Idle {
+                return Err(GameSyncError::PlayerEditError);
+            }
+        }
+        let old = player_info.0.rating;
+        self.data_store.set_player_rating(player_id, rating);
+        self.data_store.set_glicko_rating(player_id, Glicko2Rating { rating: rating as f64, ..self.data_store.get_glicko_rating(player_id).unwrap_or(Glicko2Rating::new(rating)) });
+        self.send_to_client(&player_id.to_string(), ServerEvent::RatingChanged(old, rating))?;
+        Ok(())
+    }
+
+    // Trusted result from the game server. Overrides any reports sent by the lobby leaders
+    pub fn set_match_result(&mut self, match_id: MatchID, winner: Option<LobbyID>) -> Result<(), GameSyncError> {
+        let game = self.find_match(match_id)?;
+        if game.deadline.is_some() {
+            return Err(GameSyncError::MatchReportError)
+        }
+        let outcome1 = match winner {
+            Some(winner) if winner == game.lobbies.0.lobby_id => MatchOutcome::Win,
+            Some(winner) if winner == game.lobbies.1.lobby_id => MatchOutcome::Loss,
+            Some(_) => return Err(GameSyncError::LobbyFindError),
+            None => MatchOutcome::Draw
+        };
+        self.resolve_match(game, outcome1)
+    }
+
+    fn resolve_match(&mut self, game: Match, outcome1: MatchOutcome) -> Result<(), GameSyncError> {
+        println!("Match {} in region {:?} resolved. Lobby {} {:?}", game.match_id, game.region, game.lobbies.0.lobby_id, outcome1);
+        self.data_store.remove_match(game.match_id);
+        if game.mode != GameMode::Competitive { // Ratings and leaderboards only apply to competitive matches
+            return Ok(())
+        }
+        for player_id in game.lobbies.0.player_list.iter().chain(game.lobbies.1.player_list.iter()) {
+            self.data_store.add_leaderboard_player(game.region.clone(), game.mode, *player_id);
+        }
+
+        let (lobby1, lobby2) = &game.lobbies;
+        let team1 = self.get_team_ratings(&lobby1.player_list);
+        let team2 = self.get_team_ratings(&lobby2.player_list);
+        let changes1 = self.compute_rating_changes(&team1, &team2, outcome1.score());
+        let changes2 = self.compute_rating_changes(&team2, &team1, outcome1.opposite().score());
+
+        for (player_id, old, new) in changes1.into_iter().chain(changes2) {
+            let new_rating = new.rating.round().max(0.0) as usize;
+            self.data_store.set_player_rating(player_id, new_rating);
+            self.data_store.set_glicko_rating(player_id, new);
+            self.add_ranked_match(player_id);
+            if let Err(e) = self.send_to_client(&player_id.to_string(), ServerEvent::RatingChanged(old, new_rating)) {
+                print_error(e); // Player may have disconnected since the match. Keep updating the others
+            }
+        }
+        Ok(())
+    }
+
+    // Accounts that went offline since the match are rated too. Disconnected guests are skipped
+    fn get_team_ratings(&mut self, player_list: &[PlayerID]) -> Vec<(PlayerID, usize, Glicko2Rating)> {
+        player_list.iter().filter_map(|player_id| {
+            let player = self.data_store.get_player_info(*player_id).map(|(player, _)| player)
+                .or_else(|| self.data_store.get_account_player(*player_id))?;
+            let glicko = self.data_store.get_glicko_rating(*player_id).unwrap_or(Glicko2Rating::new(player.rating));
+            Some((*player_id, player.rating, glicko))
+        }).collect()
+    }
+
+    // Each player is rated against a composite opponent with the opposing team's average rating and deviation
+    fn compute_rating_changes(&self, team: &[(PlayerID, usize, Glicko2Rating)], opponents: &[(PlayerID, usize, Glicko2Rating)], score: f64) -> Vec<(PlayerID, usize, Glicko2Rating)> {
+        if opponents.is_empty() {
+            return Vec::new()
+        }
+        let count = opponents.len() as f64;
+        let opponent = Glicko2Rating {
+            rating: opponents.iter().map(|(_, _, glicko)| glicko.rating).sum::<f64>() / count,
+            deviation: opponents.iter().map(|(_, _, glicko)| glicko.deviation).sum::<f64>() / count,
+            volatility: 0.0,
+        };
+
+        team.iter().map(|(player_id, old, glicko)| {
+            let new = match self.data_store.rating_model() {
+                RatingModel::Elo { k_factor } => {
+                    Glicko2Rating { rating: elo_update(glicko.rating, opponent.rating, score, k_factor), ..*glicko }
+                },
+                RatingModel::Glicko2 { tau } => glicko2_update(*glicko, opponent, score, tau)
+            };
+            (*player_id, *old, new)
+        }).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn elo_moves_ratings_towards_the_result() {
+        let winner = elo_update(1500.0, 1500.0, 1.0, 32.0);
+        let loser = elo_update(1500.0, 1500.0, 0.0, 32.0);
+        assert!((winner - 1516.0).abs() < 1e-9);
+        assert!((loser - 1484.0).abs() < 1e-9);
+        assert!((elo_update(1500.0, 1500.0, 0.5, 32.0) - 1500.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn elo_rewards_upsets_more() {
+        let upset = elo_update(1400.0, 1600.0, 1.0, 32.0) - 1400.0;
+        let expected_win = elo_update(1600.0, 1400.0, 1.0, 32.0) - 1600.0;
+        assert!(upset > expected_win);
+    }
+
+    // Player and first opponent from the example in Glickman's paper
+    #[test]
+    fn glicko2_winner_gains_and_becomes_more_certain() {
+        let player = Glicko2Rating { rating: 1500.0, deviation: 200.0, volatility: 0.06 };
+        let opponent = Glicko2Rating { rating: 1400.0, deviation: 30.0, volatility: 0.06 };
+        let updated = glicko2_update(player, opponent, 1.0, 0.5);
+        assert!(updated.rating > player.rating);
+        assert!(updated.deviation < player.deviation);
+        assert!((updated.volatility - 0.06).abs() < 0.001);
+    }
+
+    #[test]
+    fn glicko2_loser_drops() {
+        let player = Glicko2Rating::new(1500);
+        let updated = glicko2_update(player, Glicko2Rating::new(1500), 0.0, 0.5);
+        assert!(updated.rating < player.rating);
+        assert!(updated.deviation < player.deviation);
+    }
+}
@@ -0,0 +1,87 @@
+use std::collections::HashMap;
+use crate::error::GameSyncError;
+use crate::lobby::{*};
+use crate::networking::{ServerEvent, Websocket};
+
+// Assigns each player a role from their ranked preferences without exceeding the role counts of the composition.
+// Players without preferences can fill any role. Returns the assignment with the lowest total preference rank,
+// or None if the players can not be fitted into the composition
+pub fn assign_roles(players: &[(PlayerID, Vec<String>)], composition: &[(String, usize)]) -> Option<HashMap<PlayerID, String>> {
+    let mut open_slots: HashMap<&str, usize> = HashMap::new();
+    for (role, count) in composition {
+        *open_slots.entry(role.as_str()).or_default() += count;
+    }
+    let mut current = Vec::with_capacity(players.len());
+    let mut best = None;
+    search_roles(players, composition, 0, &mut open_slots, &mut current, &mut best);
+    best.map(|(_, roles)| players.iter().zip(roles).map(|((player_id, _), role)| (*player_id, role.to_string())).collect())
+}
+
+fn search_roles<'a>(
+    players: &[(PlayerID, Vec<String>)],
+    composition: &'a [(String, usize)],
+    cost: usize,
+    open_slots: &mut HashMap<&'a str, usize>,
+    current: &mut Vec<&'a str>,
+    best: &mut Option<(usize, Vec<&'a str>)>,
+) {
+    if best.as_ref().is_some_and(|(best_cost, _)| cost >= *best_cost) {
+        return
+    }
+    let Some((_, preferences)) = players.get(current.len()) else {
+        *best = Some((cost, current.clone()));
+        return
+    };
+    let candidates: Vec<(usize, &'a str)> = if preferences.is_empty() {
+        composition.iter().map(|(role, _)| (0, role.as_str())).collect()
+    } else {
+        preferences.iter().enumerate()
+            .filter_map(|(rank, preferred)| composition.iter().find(|(role, _)| role == preferred).map(|(role, _)| (rank, role.as_str())))
+            .collect()
+    };
+    for (rank, role) in candidates {
+        match open_slots.get_mut(role) {
+            Some(slots) if *slots > 0 => *slots -= 1,
+            _ => continue
+        }
+        current.push(role);
+        search_roles(players, composition, cost + rank, open_slots, current, best);
+        current.pop();
+        if let Some(slots) = open_slots.get_mut(role) {
+            *slots += 1;
+        }
+    }
+}
+
+impl Websocket {
+    pub fn set_role_preferences(&mut self, player_id: PlayerID, roles: Vec<String>) -> Result<(), GameSyncError> {
+        let (mut player, lobby_id) = self.find_player(player_id)?;
+        if let Some(lobby_id) = lobby_id { // Teams are formed from the preferences at queue time
+            let region = self.find_region_lobby(lobby_id)?;
+            if self.find_lobby(region, lobby_id)?.status != LobbyStatus::Idle {
+                return Err(GameSyncError::RoleError)
+            }
+        }
+        let composition = self.data_store.role_composition();
+        if !roles.iter().all(|preferred| composition.iter().any(|(role, _)| role == preferred)) {
+            return Err(GameSyncError::RoleError)
+        }
+        player.role_preferences = roles;
+        self.data_store.edit_player(player_id, Some(player), None);
+        self.send_to_client(&player_id.to_string(), ServerEvent::PlayerEdited(player_id))?;
+        Ok(())
+    }
+
+    // Role each player would play under the configured composition. Always an empty assignment when no composition is set
+    pub(crate) fn get_role_assignment(&self, player_list: &[PlayerID]) -> Option<HashMap<PlayerID, String>> {
+        let composition = self.data_store.role_composition();
+        if composition.is_empty() {
+            return Some(HashMap::new())
+        }
+        let players: Vec<(PlayerID, Vec<String>)> = player_list.iter().map(|player_id| {
+            let preferences = self.data_store.get_player_info(*player_id).map(|(player, _)| player.role_preferences).unwrap_or_default();
+            (*player_id, preferences)
+        }).collect();
+        assign_roles(&players, &composition)
+    }
+}
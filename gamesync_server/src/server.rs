@@ -1,7 +1,12 @@
+use std::collections::HashMap;
+use uuid::Uuid;
 use crate::error::GameSyncError;
+use crate::lobby::{Channel, GameMode, Leaderboard, LobbyID, MatchID, PlayerID, RankInfo, Region};
+use crate::moderation::Report;
 use crate::networking::Websocket;
 use crate::server_params::ServerParams;
 
+#[derive(Clone)] // Clones share the same server state, so server-side APIs can be called while another clone processes messages
 pub struct GameServer {
     websocket_server: Websocket,
 }
@@ -17,4 +22,53 @@ impl GameServer {
     pub fn process_messages(&mut self) {
         self.websocket_server.process_messages();
     }
-}
\ No newline at end of file
+
+    // Trusted match result reported by the game server. A winner of None is a draw
+    pub fn report_match_result(&mut self, match_id: MatchID, winner: Option<LobbyID>) -> Result<(), GameSyncError> {
+        self.websocket_server.set_match_result(match_id, winner)
+    }
+
+    // Ratings can only be changed by the server or by match results, never by clients
+    pub fn set_rating(&mut self, player_id: PlayerID, rating: usize) -> Result<(), GameSyncError> {
+        self.websocket_server.set_rating(player_id, rating)
+    }
+
+    pub fn get_rank_info(&mut self, player_id: PlayerID) -> Result<RankInfo, GameSyncError> {
+        self.websocket_server.get_rank_info(player_id)
+    }
+
+    // Archives the current season's ranks, soft resets ratings and starts the next season. Returns the new season number
+    pub fn end_season(&mut self) -> Result<usize, GameSyncError> {
+        self.websocket_server.end_season()
+    }
+
+    // Page of the leaderboard starting at offset, e.g. for a website. At most 100 entries are returned
+    pub fn get_leaderboard(&self, region: Region, mode: GameMode, offset: usize, limit: usize) -> Result<Leaderboard, GameSyncError> {
+        self.websocket_server.leaderboard_page(region, mode, offset, limit)
+    }
+
+    // Claims the Authenticator returned for the player's connection. None if the player did not authenticate
+    pub fn get_claims(&self, player_id: PlayerID) -> Option<HashMap<String, String>> {
+        self.websocket_server.data_store.get_claims(player_id)
+    }
+
+    // Adds a server-managed channel, e.g. for a guild created after the server started
+    pub fn add_channel(&mut self, channel: Channel) -> Result<(), GameSyncError> {
+        self.websocket_server.add_server_channel(channel)
+    }
+
+    // Open reports, oldest first
+    pub fn get_reports(&self) -> Vec<Report> {
+        self.websocket_server.data_store.get_reports()
+    }
+
+    // Closes a report once it has been handled. Returns the report if it was open
+    pub fn resolve_report(&mut self, report_id: Uuid) -> Option<Report> {
+        self.websocket_server.data_store.remove_report(report_id)
+    }
+
+    // Members are sent ChannelLeft
+    pub fn remove_channel(&mut self, name: &str) -> Result<(), GameSyncError> {
+        self.websocket_server.remove_channel(name)
+    }
+}
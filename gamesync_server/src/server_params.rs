@@ -1,4 +1,54 @@
-#[derive(Debug, Clone, Copy)]
+use std::time::Duration;
+use crate::lobby::Region;
+use crate::rating::RatingModel;
+use crate::ranked::RankedParams;
+use crate::penalties::PenaltyParams;
+use crate::auth::{AuthProvider, Authenticator};
+use crate::profiles::ProfileRules;
+use crate::chat::ChatParams;
+use std::sync::Arc;
+
+#[derive(Debug, Clone)]
 pub struct ServerParams {
     pub player_count: usize,
-}
\ No newline at end of file
+    pub regions: Vec<Region>, // Regions lobbies can be created in. Each region gets its own queues
+    pub initial_rating: usize, // Rating given to newly connected players
+    pub rating_model: RatingModel, // Used to update ratings from competitive match results
+    pub ready_check: Option<Duration>, // Time players have to accept a found match. None skips the ready check
+    pub match_expiry: Option<Duration>, // Time a started match waits for its result before it is dropped unrated. None keeps it until it is reported
+    pub penalties: PenaltyParams, // Queue lockouts and rating loss for dodging or abandoning matches
+    pub backfill: bool, // Queued solo players replace players who leave a match in progress
+    pub block_lobby_joins: bool, // Players can not join a lobby led by someone who blocked them
+    pub queue_status_interval: Option<Duration>, // How often queued lobbies are sent their QueueStatus. None disables the pushes
+    pub served_regions: Vec<Region>, // Regions advertised to clients in Pong responses, e.g. only the ones this host is close to. Empty advertises regions
+    pub role_composition: Vec<(String, usize)>, // Players needed per role in every team. Counts must add up to player_count. Empty disables roles
+    pub ranked: RankedParams, // Tiers, placements and season resets shown on top of competitive ratings
+    pub auth: Option<Arc<dyn AuthProvider>>, // Checks Login credentials. None disables logins, so every connection stays a guest
+    pub authenticator: Option<Arc<dyn Authenticator>>, // Checks the token every connection must Authenticate with first. None lets anyone connect as a guest
+    pub profiles: ProfileRules, // Display name and metadata limits for EditPlayer
+    pub chat: ChatParams, // Direct message and lobby chat history limits
+}
+
+impl Default for ServerParams {
+    fn default() -> Self {
+        ServerParams {
+            player_count: 2,
+            regions: ["NA", "EU", "SA", "MEA", "AS", "AU"].into_iter().map(Region::new).collect(),
+            initial_rating: 1000,
+            rating_model: RatingModel::Elo { k_factor: 32.0 },
+            ready_check: None,
+            match_expiry: Some(Duration::from_secs(4 * 60 * 60)),
+            penalties: PenaltyParams::default(),
+            backfill: false,
+            block_lobby_joins: true,
+            queue_status_interval: Some(Duration::from_secs(10)),
+            served_regions: Vec::new(),
+            role_composition: Vec::new(),
+            ranked: RankedParams::default(),
+            auth: None,
+            authenticator: None,
+            profiles: ProfileRules::default(),
+            chat: ChatParams::default(),
+        }
+    }
+}
@@ -0,0 +1,287 @@
+use std::collections::{HashMap, HashSet};
+use std::f64::consts::PI;
+use std::fmt;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+use crate::lobby::{*};
+use crate::server_params::ServerParams;
+use crate::store::DataStore;
+
+// Offline matchmaking simulation. Drives the same DataStore queues the server uses with synthetic lobbies
+// and reports match quality, wait times and throughput so thresholds can be tuned without real players
+
+#[derive(Debug, Clone, Copy)]
+pub enum RatingDistribution {
+    Uniform { min: usize, max: usize },
+    Normal { mean: f64, std_dev: f64 },
+}
+
+// How a simulated lobby leader picks the threshold sent with each CheckMatch
+#[derive(Debug, Clone, Copy)]
+pub enum ThresholdStrategy {
+    Fixed(usize),
+    Widening { initial: usize, step: usize, interval: Duration, max: usize }, // Threshold grows by step every interval spent in queue
+}
+
+impl ThresholdStrategy {
+    pub fn threshold(&self, waited: Duration) -> usize {
+        match *self {
+            ThresholdStrategy::Fixed(threshold) => threshold,
+            ThresholdStrategy::Widening { initial, step, interval, max } => {
+                let steps = if interval.is_zero() { 0 } else { (waited.as_millis() / interval.as_millis()) as usize };
+                initial.saturating_add(step.saturating_mul(steps)).min(max)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SimulationParams {
+    pub lobby_count: usize, // Total number of lobbies that will join the queue
+    pub player_count: usize, // Players per lobby
+    pub arrival_rate: f64, // Average lobbies joining the queue per simulated second
+    pub rating_distribution: RatingDistribution,
+    pub mode: GameMode,
+    pub strategy: ThresholdStrategy,
+    pub tick: Duration, // Simulated time between two CheckMatch rounds. Raised to MIN_TICK, as a zero tick would never end the run
+    pub max_wait: Duration, // Lobbies still unmatched after this long leave the queue
+    pub seed: u64,
+}
+
+impl Default for SimulationParams {
+    fn default() -> Self {
+        SimulationParams {
+            lobby_count: 1000,
+            player_count: 2,
+            arrival_rate: 5.0,
+            rating_distribution: RatingDistribution::Normal { mean: 1000.0, std_dev: 200.0 },
+            mode: GameMode::Competitive,
+            strategy: ThresholdStrategy::Widening { initial: 50, step: 50, interval: Duration::from_secs(10), max: 500 },
+            tick: Duration::from_secs(1),
+            max_wait: Duration::from_secs(300),
+            seed: 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Percentiles<T> {
+    pub p50: T,
+    pub p90: T,
+    pub p99: T,
+    pub max: T,
+}
+
+impl<T: Copy + Ord> Percentiles<T> {
+    fn from_samples(mut samples: Vec<T>) -> Option<Self> {
+        samples.sort();
+        let last = samples.len().checked_sub(1)?;
+        let at = |p: f64| samples[((last as f64) * p).round() as usize];
+        Some(Percentiles { p50: at(0.5), p90: at(0.9), p99: at(0.99), max: samples[last] })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SimulationReport {
+    pub matches: usize,
+    pub unmatched_lobbies: usize, // Lobbies that hit max_wait without a match
+    pub rating_gaps: Option<Percentiles<usize>>, // Difference between the two lobbies' average ratings
+    pub wait_times: Option<Percentiles<Duration>>, // Simulated time each matched lobby spent in queue
+    pub simulated_time: Duration,
+    pub throughput: f64, // Matches per simulated minute, up to the last match found
+    pub match_checks: usize, // Number of queue checks performed
+    pub check_time: Duration, // Wall-clock time spent inside the queue checks
+}
+
+impl fmt::Display for SimulationReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Matches: {} ({} lobbies left unmatched)", self.matches, self.unmatched_lobbies)?;
+        match &self.rating_gaps {
+            Some(gaps) => writeln!(f, "Rating gap: p50 {} p90 {} p99 {} max {}", gaps.p50, gaps.p90, gaps.p99, gaps.max)?,
+            None => writeln!(f, "Rating gap: no matches")?,
+        }
+        match &self.wait_times {
+            Some(waits) => writeln!(f, "Wait time: p50 {:?} p90 {:?} p99 {:?} max {:?}", waits.p50, waits.p90, waits.p99, waits.max)?,
+            None => writeln!(f, "Wait time: no matches")?,
+        }
+        writeln!(f, "Throughput: {:.2} matches per minute over {:?} simulated", self.throughput, self.simulated_time)?;
+        let per_check = if self.match_checks == 0 { Duration::ZERO } else { self.check_time / self.match_checks as u32 };
+        write!(f, "Queue checks: {} in {:?} ({:?} per check)", self.match_checks, self.check_time, per_check)
+    }
+}
+
+// Small xorshift generator so runs are reproducible from a seed without pulling in a rand dependency
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        Rng(seed.max(1))
+    }
+
+    fn next_f64(&mut self) -> f64 { // Uniform in [0, 1)
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    fn rating(&mut self, distribution: RatingDistribution) -> usize {
+        match distribution {
+            RatingDistribution::Uniform { min, max } => min + (self.next_f64() * (max.saturating_sub(min) + 1) as f64) as usize,
+            RatingDistribution::Normal { mean, std_dev } => { // Box-Muller transform
+                let u1 = 1.0 - self.next_f64();
+                let u2 = self.next_f64();
+                let z = (-2.0 * u1.ln()).sqrt() * (2.0 * PI * u2).cos();
+                (mean + z * std_dev).round().max(0.0) as usize
+            }
+        }
+    }
+
+    fn interarrival(&mut self, rate: f64) -> Duration { // Exponential gaps give Poisson arrivals
+        if rate <= 0.0 {
+            return Duration::ZERO
+        }
+        Duration::from_secs_f64(-(1.0 - self.next_f64()).ln() / rate)
+    }
+}
+
+pub const MIN_TICK: Duration = Duration::from_millis(1);
+
+pub fn run_simulation(params: &SimulationParams) -> SimulationReport {
+    let tick = params.tick.max(MIN_TICK);
+    let region = Region::new("simulation");
+    let mut data_store = DataStore::new(ServerParams { player_count: params.player_count, regions: vec![region.clone()], ..Default::default() });
+    let mut rng = Rng::new(params.seed);
+
+    // Arrival times are drawn up front so the queue checks do not affect the random sequence
+    let mut arrivals = Vec::with_capacity(params.lobby_count);
+    let mut next_arrival = Duration::ZERO;
+    for _ in 0..params.lobby_count {
+        next_arrival += rng.interarrival(params.arrival_rate);
+        arrivals.push(next_arrival);
+    }
+
+    let mut queued: Vec<LobbyID> = Vec::new(); // Arrival order
+    let mut queued_at: HashMap<LobbyID, Duration> = HashMap::new();
+    let mut rating_gaps = Vec::new();
+    let mut wait_times = Vec::new();
+    let mut unmatched_lobbies = 0;
+    let mut match_checks = 0;
+    let mut check_time = Duration::ZERO;
+    let mut arrived = 0;
+    let mut now = Duration::ZERO;
+    let mut last_match = Duration::ZERO;
+
+    while arrived < arrivals.len() || !queued.is_empty() {
+        while arrived < arrivals.len() && arrivals[arrived] <= now {
+            let lobby = create_lobby(&mut data_store, &mut rng, params, &region);
+            queued.push(lobby.lobby_id);
+            queued_at.insert(lobby.lobby_id, now);
+            match params.mode {
+                GameMode::Casual => data_store.add_casual_lobby(region.clone(), lobby),
+                GameMode::Competitive => data_store.add_competitive_lobby(region.clone(), lobby),
+            }
+            arrived += 1;
+        }
+
+        let mut left_queue = HashSet::new();
+        for lobby_id in queued.clone() {
+            if left_queue.contains(&lobby_id) {
+                continue;
+            }
+            let waited = now - queued_at[&lobby_id];
+            if waited > params.max_wait { // Lobby gives up on the queue
+                match params.mode {
+                    GameMode::Casual => data_store.remove_casual_lobby(region.clone(), lobby_id),
+                    GameMode::Competitive => data_store.remove_competitive_lobby(region.clone(), lobby_id),
+                }
+                remove_lobby(&data_store, &region, lobby_id);
+                left_queue.insert(lobby_id);
+                unmatched_lobbies += 1;
+                continue;
+            }
+
+            // Mirrors check_match: the threshold is stored on the lobby so the other side of a match can see it
+            let threshold = params.strategy.threshold(waited);
+            if let Some(mut lobby) = data_store.get_lobby(region.clone(), lobby_id) {
+                lobby.queue_threshold = threshold;
+                let _ = data_store.edit_lobby(region.clone(), lobby_id, lobby);
+            }
+            let started = Instant::now();
+            let result = match params.mode {
+                GameMode::Casual => data_store.check_casual_lobby(region.clone(), lobby_id),
+                GameMode::Competitive => data_store.check_competitive_lobby(region.clone(), lobby_id, threshold),
+            };
+            check_time += started.elapsed();
+            match_checks += 1;
+
+            if let Some((lobby1, lobby2)) = result {
+                let rating1 = data_store.get_lobby_average_rating(region.clone(), lobby1.lobby_id);
+                let rating2 = data_store.get_lobby_average_rating(region.clone(), lobby2.lobby_id);
+                rating_gaps.push(rating1.abs_diff(rating2));
+                last_match = now;
+                for lobby in [lobby1, lobby2] {
+                    wait_times.push(now - queued_at[&lobby.lobby_id]);
+                    remove_lobby(&data_store, &region, lobby.lobby_id);
+                    left_queue.insert(lobby.lobby_id);
+                }
+            }
+        }
+        queued.retain(|lobby_id| !left_queue.contains(lobby_id));
+        now += tick;
+    }
+
+    let matches = rating_gaps.len();
+    let minutes = last_match.as_secs_f64() / 60.0;
+    SimulationReport {
+        matches,
+        unmatched_lobbies,
+        rating_gaps: Percentiles::from_samples(rating_gaps),
+        wait_times: Percentiles::from_samples(wait_times),
+        simulated_time: now,
+        throughput: if minutes > 0.0 { matches as f64 / minutes } else { 0.0 },
+        match_checks,
+        check_time,
+    }
+}
+
+fn create_lobby(data_store: &mut DataStore, rng: &mut Rng, params: &SimulationParams, region: &Region) -> Lobby {
+    let player_list: Vec<PlayerID> = (0..params.player_count.max(1)).map(|_| {
+        let player_id = Uuid::new_v4();
+        let rating = rng.rating(params.rating_distribution);
+        data_store.add_player(player_id, Player { player_id, rating, profile: PlayerProfile::default(), role_preferences: Vec::new() });
+        player_id
+    }).collect();
+    let lobby_id = Uuid::new_v4();
+    let lobby = Lobby {
+        lobby_id,
+        params: LobbyParams {
+            name: String::new(),
+            visibility: Visibility::Public,
+            region: region.clone(),
+            mode: params.mode,
+            fallback_regions: Vec::new(),
+            fallback_delay: Duration::ZERO,
+            hard_tags: HashMap::new(),
+            soft_tags: HashMap::new(),
+        },
+        leader: player_list[0],
+        status: LobbyStatus::Queueing,
+        player_list,
+        queue_threshold: 0,
+        parties: Vec::new(),
+    };
+    data_store.create_lobby(region.clone(), lobby_id, lobby.clone());
+    data_store.create_region_lobby(lobby_id, region.clone());
+    lobby
+}
+
+fn remove_lobby(data_store: &DataStore, region: &Region, lobby_id: LobbyID) {
+    if let Some(lobby) = data_store.get_lobby(region.clone(), lobby_id) {
+        for player_id in lobby.player_list {
+            data_store.delete_player(player_id);
+        }
+    }
+    let _ = data_store.delete_lobby(region.clone(), lobby_id);
+    let _ = data_store.delete_region_lobby(lobby_id);
+}
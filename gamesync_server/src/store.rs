@@ -1,14 +1,42 @@
 use message_io::network::Endpoint;
-use strum::IntoEnumIterator;
 use uuid::Uuid;
-use crate::lobby::{Lobby, Player, Region, Visibility, PlayerID, LobbyID};
+use crate::lobby::{Lobby, LobbyParams, Player, Region, Visibility, PlayerID, LobbyID, Match, MatchID, MatchOutcome, GameMode, LeaderboardEntry, LobbyStatus, PlayerProfile, Presence, DirectMessage, LobbyChatMessage, PlayerSummary, Channel, ChannelKind};
+use crate::rating::{Glicko2Rating, RatingModel};
+use crate::ranked::{RankedParams, RankedRecord};
+use crate::penalties::{PenaltyParams, PenaltyRecord};
 use crate::server_params::ServerParams;
-use dashmap::DashMap;
+use crate::auth::{AuthProvider, Authenticator};
+use crate::profiles::ProfileRules;
+use crate::chat::ChatParams;
+use crate::filters::{MessageFilter, MessageKind};
+use crate::moderation::{Report, ReportedMessage};
+use dashmap::{DashMap, DashSet};
+use dashmap::mapref::entry::Entry;
 use std::cmp::Ordering;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering as AtomicOrdering};
 use crate::error::GameSyncError;
-use std::collections::VecDeque;
+use std::cmp::Reverse;
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+use std::time::{Duration, SystemTime};
 
+const WAIT_HISTORY_SIZE: usize = 50; // Number of recent match wait times kept per queue
+
+type LeaderboardEntries = BTreeSet<(Reverse<usize>, PlayerID)>; // Ordered by rating, highest first
+
+// Index of the first candidate whose hard tags agree with the lobby's, preferring the most shared soft tags
+fn pick_by_tags<'a>(params: &LobbyParams, candidates: impl Iterator<Item = (usize, &'a LobbyParams)>) -> Option<usize> {
+    let mut best: Option<(usize, usize)> = None;
+    for (index, other) in candidates.filter(|(_, other)| params.hard_tags_agree(other)) {
+        let overlap = params.soft_tag_overlap(other);
+        if best.is_none_or(|(_, best_overlap)| overlap > best_overlap) {
+            best = Some((index, overlap));
+        }
+    }
+    best.map(|(index, _)| index)
+}
+
+#[derive(Clone)]
 pub struct DataStore {
     user_endpoint_map: Arc<DashMap<Uuid, Endpoint>>,
     endpoint_user_map: Arc<DashMap<Endpoint, Uuid>>,
@@ -17,6 +45,38 @@ pub struct DataStore {
     player_map: Arc<DashMap<PlayerID, (Player, Option<LobbyID>)>>,
     competitive_queue_map: Arc<DashMap<Region, Vec<Lobby>>>,
     casual_queue_map: Arc<DashMap<Region, VecDeque<Lobby>>>,
+    match_map: Arc<DashMap<MatchID, Match>>,
+    glicko_map: Arc<DashMap<PlayerID, Glicko2Rating>>,
+    queue_penalty_map: Arc<DashMap<PlayerID, SystemTime>>,
+    penalty_record_map: Arc<DashMap<PlayerID, PenaltyRecord>>,
+    queue_time_map: Arc<DashMap<LobbyID, SystemTime>>,
+    wait_history_map: Arc<DashMap<(Region, GameMode), VecDeque<Duration>>>,
+    party_queue_map: Arc<DashMap<(Region, GameMode), Vec<LobbyID>>>,
+    party_team_map: Arc<DashMap<LobbyID, LobbyID>>,
+    backfill_map: Arc<DashMap<(Region, GameMode), Vec<LobbyID>>>,
+    friend_map: Arc<DashMap<PlayerID, HashSet<PlayerID>>>,
+    friend_request_map: Arc<DashMap<PlayerID, HashSet<PlayerID>>>, // Receiver -> Senders
+    presence_map: Arc<DashMap<PlayerID, Presence>>, // Last presence sent to friends
+    block_map: Arc<DashMap<PlayerID, HashSet<PlayerID>>>, // Blocker -> Blocked players
+    account_player_map: Arc<DashMap<PlayerID, Player>>, // Logged in accounts' players, kept while they are offline
+    pending_endpoints: Arc<DashSet<Endpoint>>, // Connections that have not been accepted by the Authenticator yet
+    claims_map: Arc<DashMap<PlayerID, HashMap<String, String>>>, // Claims returned by the Authenticator
+    display_name_map: Arc<DashMap<String, PlayerID>>, // Lowercase display name -> Player using it
+    conversation_map: Arc<DashMap<(PlayerID, PlayerID), VecDeque<DirectMessage>>>, // Ordered pair of players -> Recent messages, oldest first
+    offline_message_map: Arc<DashMap<PlayerID, VecDeque<DirectMessage>>>, // Messages waiting for an offline account
+    next_message_id: Arc<AtomicU64>,
+    lobby_chat_map: Arc<DashMap<LobbyID, VecDeque<LobbyChatMessage>>>, // Recent lobby messages, oldest first
+    channel_map: Arc<DashMap<String, Channel>>,
+    channel_member_map: Arc<DashMap<String, HashSet<PlayerID>>>,
+    player_channel_map: Arc<DashMap<PlayerID, HashSet<String>>>, // Channels each player is in
+    lobby_mute_map: Arc<DashMap<(LobbyID, PlayerID), SystemTime>>, // Lobby, Muted player -> Mute end
+    recent_message_map: Arc<DashMap<PlayerID, VecDeque<ReportedMessage>>>, // Context attached to reports, oldest first
+    report_map: Arc<DashMap<Uuid, Report>>, // Open reports
+    presence_dirty: Arc<DashSet<PlayerID>>, // Players whose lobby or connection changed since the last presence update
+    ranked_map: Arc<DashMap<PlayerID, RankedRecord>>,
+    leaderboard_map: Arc<DashMap<(Region, GameMode), LeaderboardEntries>>,
+    player_leaderboard_map: Arc<DashMap<PlayerID, Vec<(Region, GameMode)>>>, // Leaderboards each player is on
+    season: Arc<AtomicUsize>,
     server_params: ServerParams,
 }
 
@@ -28,14 +88,44 @@ impl DataStore {
         let new_competitive_queue_map: Arc<DashMap<Region, Vec<Lobby>>> = Arc::new(DashMap::new());
         let new_casual_queue_map: Arc<DashMap<Region, VecDeque<Lobby>>> = Arc::new(DashMap::new());
 
-        for region in Region::iter() {
-            new_global_lobby_map.entry(region).or_insert_with(|| Arc::new(DashMap::new()));
-            new_competitive_queue_map.entry(region).or_insert_with(|| Vec::new());
-            new_casual_queue_map.entry(region).or_insert_with(|| VecDeque::new());
+        for region in server_params.regions.iter() {
+            new_global_lobby_map.entry(region.clone()).or_insert_with(|| Arc::new(DashMap::new()));
+            new_competitive_queue_map.entry(region.clone()).or_default();
+            new_casual_queue_map.entry(region.clone()).or_default();
         }
         let new_region_lobby_map: Arc<DashMap<Uuid, Region>> = Arc::new(DashMap::new());
         let new_player_map: Arc<DashMap<PlayerID, (Player, Option<LobbyID>)>> = Arc::new(DashMap::new());
-
+        let new_match_map: Arc<DashMap<MatchID, Match>> = Arc::new(DashMap::new());
+        let new_glicko_map: Arc<DashMap<PlayerID, Glicko2Rating>> = Arc::new(DashMap::new());
+        let new_queue_penalty_map: Arc<DashMap<PlayerID, SystemTime>> = Arc::new(DashMap::new());
+        let new_penalty_record_map: Arc<DashMap<PlayerID, PenaltyRecord>> = Arc::new(DashMap::new());
+        let new_queue_time_map: Arc<DashMap<LobbyID, SystemTime>> = Arc::new(DashMap::new());
+        let new_wait_history_map: Arc<DashMap<(Region, GameMode), VecDeque<Duration>>> = Arc::new(DashMap::new());
+        let new_party_queue_map: Arc<DashMap<(Region, GameMode), Vec<LobbyID>>> = Arc::new(DashMap::new());
+        let new_party_team_map: Arc<DashMap<LobbyID, LobbyID>> = Arc::new(DashMap::new());
+        let new_backfill_map: Arc<DashMap<(Region, GameMode), Vec<LobbyID>>> = Arc::new(DashMap::new());
+        let new_friend_map: Arc<DashMap<PlayerID, HashSet<PlayerID>>> = Arc::new(DashMap::new());
+        let new_friend_request_map: Arc<DashMap<PlayerID, HashSet<PlayerID>>> = Arc::new(DashMap::new());
+        let new_presence_map: Arc<DashMap<PlayerID, Presence>> = Arc::new(DashMap::new());
+        let new_block_map: Arc<DashMap<PlayerID, HashSet<PlayerID>>> = Arc::new(DashMap::new());
+        let new_account_player_map: Arc<DashMap<PlayerID, Player>> = Arc::new(DashMap::new());
+        let new_claims_map: Arc<DashMap<PlayerID, HashMap<String, String>>> = Arc::new(DashMap::new());
+        let new_display_name_map: Arc<DashMap<String, PlayerID>> = Arc::new(DashMap::new());
+        let new_conversation_map: Arc<DashMap<(PlayerID, PlayerID), VecDeque<DirectMessage>>> = Arc::new(DashMap::new());
+        let new_offline_message_map: Arc<DashMap<PlayerID, VecDeque<DirectMessage>>> = Arc::new(DashMap::new());
+        let new_lobby_chat_map: Arc<DashMap<LobbyID, VecDeque<LobbyChatMessage>>> = Arc::new(DashMap::new());
+        let new_channel_map: Arc<DashMap<String, Channel>> = Arc::new(DashMap::new());
+        for channel in server_params.chat.channels.iter() {
+            new_channel_map.insert(channel.name.clone(), channel.clone());
+        }
+        let new_channel_member_map: Arc<DashMap<String, HashSet<PlayerID>>> = Arc::new(DashMap::new());
+        let new_player_channel_map: Arc<DashMap<PlayerID, HashSet<String>>> = Arc::new(DashMap::new());
+        let new_lobby_mute_map: Arc<DashMap<(LobbyID, PlayerID), SystemTime>> = Arc::new(DashMap::new());
+        let new_recent_message_map: Arc<DashMap<PlayerID, VecDeque<ReportedMessage>>> = Arc::new(DashMap::new());
+        let new_report_map: Arc<DashMap<Uuid, Report>> = Arc::new(DashMap::new());
+        let new_ranked_map: Arc<DashMap<PlayerID, RankedRecord>> = Arc::new(DashMap::new());
+        let new_leaderboard_map: Arc<DashMap<(Region, GameMode), LeaderboardEntries>> = Arc::new(DashMap::new());
+        let new_player_leaderboard_map: Arc<DashMap<PlayerID, Vec<(Region, GameMode)>>> = Arc::new(DashMap::new());
 
         Self {
             user_endpoint_map: Arc::clone(&new_user_endpoint_map),
@@ -45,7 +135,39 @@ impl DataStore {
             player_map: Arc::clone(&new_player_map),
             competitive_queue_map: Arc::clone(&new_competitive_queue_map),
             casual_queue_map: Arc::clone(&new_casual_queue_map),
-            server_params: server_params.clone()
+            match_map: Arc::clone(&new_match_map),
+            glicko_map: Arc::clone(&new_glicko_map),
+            queue_penalty_map: Arc::clone(&new_queue_penalty_map),
+            penalty_record_map: Arc::clone(&new_penalty_record_map),
+            queue_time_map: Arc::clone(&new_queue_time_map),
+            wait_history_map: Arc::clone(&new_wait_history_map),
+            party_queue_map: Arc::clone(&new_party_queue_map),
+            party_team_map: Arc::clone(&new_party_team_map),
+            backfill_map: Arc::clone(&new_backfill_map),
+            friend_map: Arc::clone(&new_friend_map),
+            friend_request_map: Arc::clone(&new_friend_request_map),
+            presence_map: Arc::clone(&new_presence_map),
+            block_map: Arc::clone(&new_block_map),
+            account_player_map: Arc::clone(&new_account_player_map),
+            pending_endpoints: Arc::new(DashSet::new()),
+            claims_map: Arc::clone(&new_claims_map),
+            display_name_map: Arc::clone(&new_display_name_map),
+            conversation_map: Arc::clone(&new_conversation_map),
+            offline_message_map: Arc::clone(&new_offline_message_map),
+            next_message_id: Arc::new(AtomicU64::new(0)),
+            lobby_chat_map: Arc::clone(&new_lobby_chat_map),
+            channel_map: Arc::clone(&new_channel_map),
+            channel_member_map: Arc::clone(&new_channel_member_map),
+            player_channel_map: Arc::clone(&new_player_channel_map),
+            lobby_mute_map: Arc::clone(&new_lobby_mute_map),
+            recent_message_map: Arc::clone(&new_recent_message_map),
+            report_map: Arc::clone(&new_report_map),
+            presence_dirty: Arc::new(DashSet::new()),
+            ranked_map: Arc::clone(&new_ranked_map),
+            leaderboard_map: Arc::clone(&new_leaderboard_map),
+            player_leaderboard_map: Arc::clone(&new_player_leaderboard_map),
+            season: Arc::new(AtomicUsize::new(1)),
+            server_params
         }
     }
 
@@ -75,6 +197,14 @@ impl DataStore {
         }
     }
 
+    // Points the endpoint at another user ID, e.g. the account it logged in to
+    pub fn rebind_user_endpoint(&self, endpoint: Endpoint, user_id: Uuid) {
+        if let Some(old_user_id) = self.endpoint_user_map.insert(endpoint, user_id) {
+            self.user_endpoint_map.remove(&old_user_id);
+        }
+        self.user_endpoint_map.insert(user_id, endpoint);
+    }
+
     pub fn remove_user_endpoint(&mut self, endpoint: Endpoint) {
         let user_id = self.get_user(endpoint);
 
@@ -92,6 +222,7 @@ impl DataStore {
             .entry(outer_key)
             .or_insert_with(|| Arc::new(DashMap::new()));
    
+        self.mark_presence_dirty(&inner_value.player_list);
         inner_map.clone().insert(inner_key, inner_value);
     }
 
@@ -107,6 +238,7 @@ impl DataStore {
 
     pub fn edit_lobby(&self, outer_key: Region, inner_key: Uuid, new_value: Lobby) -> Result<(), GameSyncError> {
         if let Some(inner_map) = self.global_lobby_map.get(&outer_key) {
+            self.mark_presence_dirty(&new_value.player_list);
             inner_map.insert(inner_key, new_value);
             Ok(())
         } else {
@@ -116,7 +248,11 @@ impl DataStore {
 
     pub fn delete_lobby(&self, outer_key: Region, inner_key: Uuid) -> Result<(), GameSyncError> {
         if let Some(inner_map) = self.global_lobby_map.get(&outer_key) {
-            inner_map.remove(&inner_key);  // Remove the entry from the inner map
+            if let Some((_, lobby)) = inner_map.remove(&inner_key) {  // Remove the entry from the inner map
+                self.mark_presence_dirty(&lobby.player_list);
+            }
+            self.lobby_chat_map.remove(&inner_key);
+            self.lobby_mute_map.retain(|(lobby_id, _), _| *lobby_id != inner_key);
             Ok(())
         } else {
             return Err(GameSyncError::LobbyFindError)
@@ -180,6 +316,7 @@ impl DataStore {
     /* <PLAYER_ID, (PLAYER, LOBBY)> HASHMAP FUNCTIONS */
     pub fn add_player(&self, player_id: Uuid, player: Player) {
         self.player_map.insert(player_id, (player, None));
+        self.presence_dirty.insert(player_id);
     }
 
     pub fn edit_player(&self, player_id: Uuid, player: Option<Player>, lobby_id: Option<LobbyID>) {
@@ -198,6 +335,9 @@ impl DataStore {
                 None => ()
             }
         });
+        if lobby_id.is_some() {
+            self.presence_dirty.insert(player_id);
+        }
     }
 
     pub fn remove_player_lobby(&self, player_id: Uuid) {
@@ -205,6 +345,7 @@ impl DataStore {
         .and_modify(|tuple| {
             tuple.1 = None
         });
+        self.presence_dirty.insert(player_id);
     }
 
     pub fn get_player_info(&self, player_id: Uuid) -> Option<(Player, Option<LobbyID>)> {
@@ -214,8 +355,24 @@ impl DataStore {
         }
     }
 
+    pub fn get_all_players(&self) -> Vec<Player> {
+        self.player_map.iter().map(|entry| entry.value().0.clone()).collect()
+    }
+
     pub fn delete_player(&self, player_id: Uuid) {
-        self.player_map.remove(&player_id);
+        self.leave_all_channels(player_id);
+        self.claims_map.remove(&player_id); // Claims only last for the connection that authenticated
+        if let Some((_, (player, _))) = self.player_map.remove(&player_id) {
+            if let Some(mut account_player) = self.account_player_map.get_mut(&player_id) { // Saved for the next login
+                *account_player = player;
+            } else { // Guests give their display name and conversations up
+                self.release_display_name(player_id, &player.profile.display_name);
+                self.conversation_map.retain(|players, _| players.0 != player_id && players.1 != player_id);
+                self.recent_message_map.remove(&player_id);
+                self.remove_leaderboard_entries(player_id, player.rating);
+            }
+        }
+        self.presence_dirty.insert(player_id);
     }
 
     #[allow(dead_code)]
@@ -234,6 +391,12 @@ impl DataStore {
         }
     }
 
+    pub fn requeue_casual_lobby(&self, region: Region, lobby: Lobby) { // Lobby goes back to the front of the queue
+        if let Some(mut region_lobbies) = self.casual_queue_map.get_mut(&region) {
+            region_lobbies.push_front(lobby);
+        }
+    }
+
     #[allow(dead_code)]
     pub fn get_casual_lobbies(&self, region: Region) -> Option<VecDeque<Lobby>> {
         self.casual_queue_map.get(&region).map(|lobbies| lobbies.clone())
@@ -246,27 +409,43 @@ impl DataStore {
             }
             let target_index = lobbies.iter().position(|l| l.lobby_id == lobby_id)?;
 
-            // Temporarily remove the target lobby from the queue
-            let lobby1 = lobbies.remove(target_index).unwrap();
+            // Match with the longest waiting lobby whose tags are compatible
+            let candidates = lobbies.iter().enumerate().filter(|(index, _)| *index != target_index).map(|(index, l)| (index, &l.params));
+            let index = pick_by_tags(&lobbies[target_index].params, candidates)?;
 
-            // Try to match with the next available lobby (from the front or back of the queue)
-            let lobby2 = if !lobbies.is_empty() {
-                lobbies.pop_front() // Remove from the front
-            } else {
-                None
-            };
+            // Remove the later lobby first so the other index stays valid
+            let (first, second) = if index < target_index { (target_index, index) } else { (index, target_index) };
+            let removed_first = lobbies.remove(first)?;
+            let removed_second = lobbies.remove(second)?;
+            return if first == target_index { Some((removed_first, removed_second)) } else { Some((removed_second, removed_first)) };
+        }
+        None
+    }
 
-            if let Some(lobby2) = lobby2 {
-                return Some((lobby1, lobby2));
-            } else {
-                // Reinsert the lobby if no match was found
-                lobbies.push_back(lobby1);
+    // Matches a lobby queueing in its own region with the longest waiting compatible lobby in another region's casual queue
+    pub fn check_casual_fallback(&self, region: Region, lobby: &Lobby) -> Option<(Lobby, Lobby)> {
+        let (index, lobby2) = {
+            let mut lobbies = self.casual_queue_map.get_mut(&region)?;
+            let index = pick_by_tags(&lobby.params, lobbies.iter().enumerate().map(|(index, l)| (index, &l.params)))?;
+            (index, lobbies.remove(index)?)
+        };
+        let lobby1 = self.casual_queue_map.get_mut(&lobby.params.region).and_then(|mut lobbies| {
+            let index = lobbies.iter().position(|l| l.lobby_id == lobby.lobby_id)?;
+            lobbies.remove(index)
+        });
+        match lobby1 {
+            Some(lobby1) => Some((lobby1, lobby2)),
+            None => { // Lobby is no longer queueing. Put the other lobby back where it was
+                if let Some(mut lobbies) = self.casual_queue_map.get_mut(&region) {
+                    lobbies.insert(index, lobby2);
+                }
+                None
             }
         }
-        None
     }
 
     pub fn remove_casual_lobby(&self, region: Region, lobby_id: Uuid) { // This is done this way to avoid shifting the entire VecDeque
+        self.queue_time_map.remove(&lobby_id);
         if let Some(mut region_lobbies) = self.casual_queue_map.get_mut(&region) {
             if let Some(index) = region_lobbies.iter().position(|l| l.lobby_id == lobby_id) {
                 // Use split_off to remove the element
@@ -291,9 +470,9 @@ impl DataStore {
     // Lobby vector is ordered by average rating
     pub fn add_competitive_lobby(&mut self, region: Region, lobby: Lobby) {
         if let Some(mut lobbies) = self.competitive_queue_map.get_mut(&region) {
-            let avg_rating = self.get_lobby_average_rating(region, lobby.lobby_id);
+            let avg_rating = self.get_lobby_average_rating(region.clone(), lobby.lobby_id);
             let position = lobbies
-                .binary_search_by(|l| self.get_lobby_average_rating(region, l.lobby_id).partial_cmp(&avg_rating).unwrap_or(Ordering::Equal))
+                .binary_search_by(|l| self.get_lobby_average_rating(region.clone(), l.lobby_id).partial_cmp(&avg_rating).unwrap_or(Ordering::Equal))
                 .unwrap_or_else(|e| e);
             lobbies.insert(position, lobby);
         }
@@ -313,14 +492,14 @@ impl DataStore {
             let target_index = lobbies.iter().position(|lobby| lobby.lobby_id == lobby_id)?;
 
             let lobby1 = lobbies[target_index].clone();
-            let avg_rating1 = self.get_lobby_average_rating(region, lobby1.lobby_id); // 1000
+            let avg_rating1 = self.get_lobby_average_rating(region.clone(), lobby1.lobby_id); // 1000
             let threshold_mod = if threshold >= avg_rating1 { avg_rating1 } else { threshold };
             let range_min = avg_rating1 - threshold_mod; // 0
             let range_max = avg_rating1 + threshold; // 3000
 
             let lower_bound = lobbies
                 .binary_search_by(|l| {
-                    if self.get_lobby_average_rating(region, l.lobby_id) < range_min {
+                    if self.get_lobby_average_rating(region.clone(), l.lobby_id) < range_min {
                         Ordering::Less
                     } else {
                         Ordering::Greater
@@ -330,7 +509,7 @@ impl DataStore {
 
             let upper_bound = lobbies
                 .binary_search_by(|l| {
-                    if self.get_lobby_average_rating(region, l.lobby_id) > range_max {
+                    if self.get_lobby_average_rating(region.clone(), l.lobby_id) > range_max {
                         Ordering::Greater
                     } else {
                         Ordering::Less
@@ -338,33 +517,57 @@ impl DataStore {
                 })
                 .unwrap_or_else(|e| e);
 
-            for i in lower_bound..upper_bound {
-                if i == target_index {
-                    continue; // Skip the target lobby itself
-                }
-
-                let lobby2 = &lobbies[i];
-                let lobby2 = self.get_lobby(lobby2.params.region, lobby2.lobby_id).unwrap();
-                let avg_rating2 = self.get_lobby_average_rating(region, lobby2.lobby_id); // 3000
+            // Among the lobbies whose threshold also covers this lobby, prefer compatible tags
+            let candidates = (lower_bound..upper_bound).filter(|i| *i != target_index).filter(|i| { // Skip the target lobby itself
+                let lobby2 = self.get_lobby(lobbies[*i].params.region.clone(), lobbies[*i].lobby_id).unwrap();
+                let avg_rating2 = self.get_lobby_average_rating(region.clone(), lobby2.lobby_id); // 3000
                 let lobby2_threshold_mod = if lobby2.queue_threshold >= avg_rating2 { avg_rating2 } else { lobby2.queue_threshold };
                 let range_min2 = avg_rating2 - lobby2_threshold_mod; // 3000
                 let range_max2 = avg_rating2 + lobby2.queue_threshold; // 3000
                 // 0 < 3000 // 3000 < 3000
-                println!("Range min2 {}, Range max2 {}, Lobby1AvgRating {}", range_min2, range_max2, avg_rating1);
-                if range_min2 <= avg_rating1 && avg_rating1 <= range_max2 {
-                    let matched_lobby2 = lobbies.remove(i);
-                    let matched_lobby1 = if i < target_index {
-                        lobbies.remove(target_index - 1)
-                    } else {
-                        lobbies.remove(target_index)
-                    };
-                    return Some((matched_lobby1.clone(), matched_lobby2.clone()));
-                }
+                range_min2 <= avg_rating1 && avg_rating1 <= range_max2
+            }).map(|i| (i, &lobbies[i].params));
+            if let Some(i) = pick_by_tags(&lobby1.params, candidates) {
+                let matched_lobby2 = lobbies.remove(i);
+                let matched_lobby1 = if i < target_index {
+                    lobbies.remove(target_index - 1)
+                } else {
+                    lobbies.remove(target_index)
+                };
+                return Some((matched_lobby1.clone(), matched_lobby2.clone()));
             }
         }
         None  // This could either mean match not found or match was already found before (hence removed from queue). Client's responsibility for stop searching once MatchFound is received OR do a check for if lobby is already InGame state
     }
 
+    // Matches a lobby queueing in its own region with a lobby from another region's competitive queue. Both thresholds must be satisfied
+    pub fn check_competitive_fallback(&self, region: Region, lobby: &Lobby, threshold: usize) -> Option<(Lobby, Lobby)> {
+        let avg_rating1 = self.get_lobby_average_rating(lobby.params.region.clone(), lobby.lobby_id);
+        let (index, lobby2) = {
+            let mut lobbies = self.competitive_queue_map.get_mut(&region)?;
+            let candidates = lobbies.iter().enumerate().filter(|(_, l)| {
+                let threshold2 = self.get_lobby(region.clone(), l.lobby_id).map(|l| l.queue_threshold).unwrap_or(0);
+                let avg_rating2 = self.get_lobby_average_rating(region.clone(), l.lobby_id);
+                avg_rating1.abs_diff(avg_rating2) <= threshold && avg_rating1.abs_diff(avg_rating2) <= threshold2
+            }).map(|(index, l)| (index, &l.params));
+            let index = pick_by_tags(&lobby.params, candidates)?;
+            (index, lobbies.remove(index))
+        };
+        let lobby1 = self.competitive_queue_map.get_mut(&lobby.params.region).and_then(|mut lobbies| {
+            let index = lobbies.iter().position(|l| l.lobby_id == lobby.lobby_id)?;
+            Some(lobbies.remove(index))
+        });
+        match lobby1 {
+            Some(lobby1) => Some((lobby1, lobby2)),
+            None => { // Lobby is no longer queueing. Put the other lobby back where it was
+                if let Some(mut lobbies) = self.competitive_queue_map.get_mut(&region) {
+                    lobbies.insert(index, lobby2);
+                }
+                None
+            }
+        }
+    }
+
     pub fn get_lobby_average_rating(&self, region: Region, lobby_id: Uuid) -> usize {
         if let Some(lobby) = self.get_lobby(region, lobby_id) {
             if lobby.player_list.len() != 0 {
@@ -386,7 +589,15 @@ impl DataStore {
         0 // Lobby not found. Should not happen -> error checking done prior to this
     }
 
+    pub fn sort_competitive_lobbies(&self) { // Restores the rating order after ratings of queued players changed
+        for mut entry in self.competitive_queue_map.iter_mut() {
+            let region = entry.key().clone();
+            entry.value_mut().sort_by_key(|lobby| self.get_lobby_average_rating(region.clone(), lobby.lobby_id));
+        }
+    }
+
     pub fn remove_competitive_lobby(&mut self, region: Region, lobby_id: Uuid) {
+        self.queue_time_map.remove(&lobby_id);
         if let Some(mut region_lobbies) = self.competitive_queue_map.get_mut(&region) {
             if let Some(index) = region_lobbies.iter().position(|l| l.lobby_id == lobby_id) {
                 region_lobbies.remove(index);
@@ -405,10 +616,705 @@ impl DataStore {
     }
 
 
+    /* PARTY QUEUE FUNCTIONS */
+    // Lobbies with fewer than lobby_size() players, in arrival order, waiting to be assembled into a team
+    pub fn add_party_lobby(&self, region: Region, mode: GameMode, lobby_id: LobbyID) {
+        self.party_queue_map.entry((region, mode)).or_default().push(lobby_id);
+    }
+
+    pub fn get_party_lobbies(&self, region: Region, mode: GameMode) -> Vec<LobbyID> {
+        self.party_queue_map.get(&(region, mode)).map(|lobbies| lobbies.clone()).unwrap_or_default()
+    }
+
+    pub fn remove_party_lobby(&self, region: Region, mode: GameMode, lobby_id: LobbyID) {
+        self.queue_time_map.remove(&lobby_id);
+        if let Some(mut lobbies) = self.party_queue_map.get_mut(&(region, mode)) {
+            lobbies.retain(|l| *l != lobby_id);
+        }
+    }
+
+    /* BACKFILL FUNCTIONS */
+    // In-game lobbies below lobby_size(), in the order they lost a player, waiting for queued solo players
+    pub fn add_backfill_lobby(&self, region: Region, mode: GameMode, lobby_id: LobbyID) {
+        let mut lobbies = self.backfill_map.entry((region, mode)).or_default();
+        if !lobbies.contains(&lobby_id) {
+            lobbies.push(lobby_id);
+        }
+    }
+
+    pub fn get_backfill_lobbies(&self, region: Region, mode: GameMode) -> Vec<LobbyID> {
+        self.backfill_map.get(&(region, mode)).map(|lobbies| lobbies.clone()).unwrap_or_default()
+    }
+
+    pub fn remove_backfill_lobby(&self, region: Region, mode: GameMode, lobby_id: LobbyID) {
+        if let Some(mut lobbies) = self.backfill_map.get_mut(&(region, mode)) {
+            lobbies.retain(|l| *l != lobby_id);
+        }
+    }
+
+    /* <LOBBY_ID, TEAM_ID> HASHMAP FUNCTIONS */
+    pub fn set_party_team(&self, lobby_id: LobbyID, team_id: LobbyID) {
+        self.party_team_map.insert(lobby_id, team_id);
+    }
+
+    pub fn get_party_team(&self, lobby_id: LobbyID) -> Option<LobbyID> {
+        self.party_team_map.get(&lobby_id).map(|entry| *entry.value())
+    }
+
+    pub fn remove_party_team(&self, lobby_id: LobbyID) {
+        self.party_team_map.remove(&lobby_id);
+    }
+
+    /* <MATCH_ID, MATCH> HASHMAP FUNCTIONS */
+    pub fn add_match(&self, game: Match) {
+        self.match_map.insert(game.match_id, game);
+    }
+
+    pub fn edit_match(&self, match_id: MatchID, game: Match) {
+        self.match_map.insert(match_id, game);
+    }
+
+    pub fn get_match(&self, match_id: MatchID) -> Option<Match> {
+        self.match_map.get(&match_id).map(|entry| entry.value().clone())
+    }
+
+    pub fn remove_match(&self, match_id: MatchID) -> Option<Match> {
+        self.match_map.remove(&match_id).map(|(_, game)| game)
+    }
+
+    // Replaces the lobby's previous report. Once both lobbies reported, the match is removed and returned for reconciling
+    pub fn add_match_report(&self, match_id: MatchID, lobby_id: LobbyID, outcome: MatchOutcome) -> Option<Match> {
+        match self.match_map.entry(match_id) {
+            Entry::Occupied(mut entry) => {
+                let game = entry.get_mut();
+                game.reports.retain(|(reporter, _)| *reporter != lobby_id);
+                game.reports.push((lobby_id, outcome));
+                if game.reports.len() < 2 {
+                    Some(game.clone())
+                } else {
+                    Some(entry.remove())
+                }
+            },
+            Entry::Vacant(_) => None
+        }
+    }
+
+    // Matches the lobby left are skipped, so a lobby back in the queue only finds its new match
+    pub fn get_lobby_match(&self, lobby_id: LobbyID) -> Option<Match> {
+        self.match_map.iter()
+        .find(|entry| (entry.value().lobbies.0.lobby_id == lobby_id || entry.value().lobbies.1.lobby_id == lobby_id) && !entry.value().left.contains(&lobby_id))
+        .map(|entry| entry.value().clone())
+    }
+
+    pub fn remove_match_player(&self, match_id: MatchID, player_id: PlayerID) {
+        if let Some(mut game) = self.match_map.get_mut(&match_id) {
+            game.lobbies.0.player_list.retain(|player| *player != player_id);
+            game.lobbies.1.player_list.retain(|player| *player != player_id);
+        }
+    }
+
+    // The match is removed once both lobbies left it
+    pub fn leave_match(&self, match_id: MatchID, lobby_id: LobbyID) {
+        if let Entry::Occupied(mut entry) = self.match_map.entry(match_id) {
+            if !entry.get().left.contains(&lobby_id) {
+                entry.get_mut().left.push(lobby_id);
+            }
+            if entry.get().left.len() >= 2 {
+                entry.remove();
+            }
+        }
+    }
+
+    /* <PLAYER_ID, LOCKOUT_END> HASHMAP FUNCTIONS */
+    pub fn add_queue_penalty(&self, player_id: PlayerID, until: SystemTime) {
+        self.queue_penalty_map.insert(player_id, until);
+    }
+
+    pub fn get_queue_penalty(&self, player_id: PlayerID) -> Option<SystemTime> {
+        match self.queue_penalty_map.get(&player_id) {
+            Some(entry) if *entry.value() > SystemTime::now() => Some(*entry.value()),
+            _ => None // Expired lockouts are ignored
+        }
+    }
+
+    pub fn get_penalty_record(&self, player_id: PlayerID) -> Option<PenaltyRecord> {
+        self.penalty_record_map.get(&player_id).map(|entry| entry.value().clone())
+    }
+
+    pub fn set_penalty_record(&self, player_id: PlayerID, record: PenaltyRecord) {
+        self.penalty_record_map.insert(player_id, record);
+    }
+
+    /* RATING FUNCTIONS */
+    pub fn set_player_rating(&self, player_id: PlayerID, rating: usize) {
+        let mut old_rating = None;
+        self.player_map.entry(player_id)
+        .and_modify(|tuple| {
+            old_rating = Some(tuple.0.rating);
+            tuple.0.rating = rating
+        });
+        if old_rating.is_none() { // Offline accounts keep rating changes for their next login
+            if let Some(mut account_player) = self.account_player_map.get_mut(&player_id) {
+                old_rating = Some(account_player.rating);
+                account_player.rating = rating;
+            }
+        }
+        if let Some(old_rating) = old_rating { // Keep the player's leaderboard entries in order
+            self.move_leaderboard_entries(player_id, old_rating, rating);
+        }
+    }
+
+    pub fn get_glicko_rating(&self, player_id: PlayerID) -> Option<Glicko2Rating> {
+        self.glicko_map.get(&player_id).map(|entry| *entry.value())
+    }
+
+    pub fn set_glicko_rating(&self, player_id: PlayerID, rating: Glicko2Rating) {
+        self.glicko_map.insert(player_id, rating);
+    }
+
+    /* RANKED FUNCTIONS */
+    pub fn get_ranked_record(&self, player_id: PlayerID) -> Option<RankedRecord> {
+        self.ranked_map.get(&player_id).map(|entry| entry.value().clone())
+    }
+
+    pub fn set_ranked_record(&self, player_id: PlayerID, record: RankedRecord) {
+        self.ranked_map.insert(player_id, record);
+    }
+
+    pub fn season(&self) -> usize {
+        self.season.load(AtomicOrdering::SeqCst)
+    }
+
+    pub fn start_next_season(&self) -> usize {
+        self.season.fetch_add(1, AtomicOrdering::SeqCst) + 1
+    }
+
+    /* FRIENDS FUNCTIONS */
+    pub fn get_friends(&self, player_id: PlayerID) -> HashSet<PlayerID> {
+        self.friend_map.get(&player_id).map(|friends| friends.clone()).unwrap_or_default()
+    }
+
+    pub fn add_friends(&self, player_id: PlayerID, friend_id: PlayerID) {
+        self.friend_map.entry(player_id).or_default().insert(friend_id);
+        self.friend_map.entry(friend_id).or_default().insert(player_id);
+    }
+
+    pub fn remove_friends(&self, player_id: PlayerID, friend_id: PlayerID) -> bool {
+        let removed = self.friend_map.get_mut(&player_id).is_some_and(|mut friends| friends.remove(&friend_id));
+        if let Some(mut friends) = self.friend_map.get_mut(&friend_id) {
+            friends.remove(&player_id);
+        }
+        removed
+    }
+
+    pub fn add_friend_request(&self, from: PlayerID, to: PlayerID) -> bool {
+        self.friend_request_map.entry(to).or_default().insert(from)
+    }
+
+    pub fn remove_friend_request(&self, from: PlayerID, to: PlayerID) -> bool {
+        self.friend_request_map.get_mut(&to).is_some_and(|mut senders| senders.remove(&from))
+    }
+
+    pub fn get_incoming_friend_requests(&self, player_id: PlayerID) -> Vec<PlayerID> {
+        self.friend_request_map.get(&player_id).map(|senders| senders.iter().copied().collect()).unwrap_or_default()
+    }
+
+    pub fn get_outgoing_friend_requests(&self, player_id: PlayerID) -> Vec<PlayerID> {
+        self.friend_request_map.iter().filter(|entry| entry.value().contains(&player_id)).map(|entry| *entry.key()).collect()
+    }
+
+    /* ACCOUNT FUNCTIONS */
+    pub fn get_account_player(&self, player_id: PlayerID) -> Option<Player> {
+        self.account_player_map.get(&player_id).map(|player| player.clone())
+    }
+
+    // Connected accounts are left out, as their current state is in the player map
+    pub fn get_offline_account_players(&self) -> Vec<Player> {
+        self.account_player_map.iter()
+        .filter(|entry| !self.player_map.contains_key(entry.key()))
+        .map(|entry| entry.value().clone())
+        .collect()
+    }
+
+    pub fn set_account_player(&self, player_id: PlayerID, player: Player) {
+        self.account_player_map.insert(player_id, player);
+    }
+
+    pub fn add_pending_endpoint(&self, endpoint: Endpoint) {
+        self.pending_endpoints.insert(endpoint);
+    }
+
+    pub fn is_pending_endpoint(&self, endpoint: Endpoint) -> bool {
+        self.pending_endpoints.contains(&endpoint)
+    }
+
+    pub fn remove_pending_endpoint(&self, endpoint: Endpoint) -> bool {
+        self.pending_endpoints.remove(&endpoint).is_some()
+    }
+
+    pub fn get_claims(&self, player_id: PlayerID) -> Option<HashMap<String, String>> {
+        self.claims_map.get(&player_id).map(|claims| claims.clone())
+    }
+
+    pub fn set_claims(&self, player_id: PlayerID, claims: HashMap<String, String>) {
+        self.claims_map.insert(player_id, claims);
+    }
+
+    /* PROFILE FUNCTIONS */
+    // Profile of a connected player or of an offline account
+    pub fn get_player_profile(&self, player_id: PlayerID) -> Option<PlayerProfile> {
+        match self.player_map.get(&player_id) {
+            Some(entry) => Some(entry.value().0.profile.clone()),
+            None => self.account_player_map.get(&player_id).map(|player| player.profile.clone())
+        }
+    }
+
+    // Only the profile is replaced, so a rating set meanwhile is kept. Returns the old profile
+    pub fn set_player_profile(&self, player_id: PlayerID, profile: PlayerProfile) -> Option<PlayerProfile> {
+        self.player_map.get_mut(&player_id).map(|mut entry| std::mem::replace(&mut entry.value_mut().0.profile, profile))
+    }
+
+    // Returns false if another player already uses the name
+    pub fn reserve_display_name(&self, player_id: PlayerID, display_name: &str) -> bool {
+        if display_name.is_empty() {
+            return true;
+        }
+        *self.display_name_map.entry(display_name.to_lowercase()).or_insert(player_id) == player_id
+    }
+
+    pub fn release_display_name(&self, player_id: PlayerID, display_name: &str) {
+        self.display_name_map.remove_if(&display_name.to_lowercase(), |_, owner| *owner == player_id);
+    }
+
+    /* DIRECT MESSAGE FUNCTIONS */
+    fn conversation_key(player_id: PlayerID, other_id: PlayerID) -> (PlayerID, PlayerID) {
+        (player_id.min(other_id), player_id.max(other_id))
+    }
+
+    pub fn add_direct_message(&self, from: PlayerID, to: PlayerID, message: String) -> DirectMessage {
+        let message_id = self.next_message_id.fetch_add(1, AtomicOrdering::SeqCst);
+        let direct_message = DirectMessage { message_id, from, to, message, sent_at: SystemTime::now() };
+        let mut conversation = self.conversation_map.entry(Self::conversation_key(from, to)).or_default();
+        conversation.push_back(direct_message.clone());
+        while conversation.len() > self.server_params.chat.conversation_history {
+            conversation.pop_front();
+        }
+        direct_message
+    }
+
+    // Latest limit messages sent before the given message ID, oldest first
+    pub fn get_conversation(&self, player_id: PlayerID, other_id: PlayerID, before: Option<u64>, limit: usize) -> Vec<DirectMessage> {
+        let Some(conversation) = self.conversation_map.get(&Self::conversation_key(player_id, other_id)) else {
+            return Vec::new();
+        };
+        let mut page: Vec<DirectMessage> = conversation.iter()
+            .rev()
+            .filter(|message| before.is_none_or(|before| message.message_id < before))
+            .take(limit)
+            .cloned()
+            .collect();
+        page.reverse();
+        page
+    }
+
+    pub fn queue_offline_message(&self, direct_message: DirectMessage) {
+        let mut queue = self.offline_message_map.entry(direct_message.to).or_default();
+        queue.push_back(direct_message);
+        while queue.len() > self.server_params.chat.offline_messages {
+            queue.pop_front();
+        }
+    }
+
+    pub fn take_offline_messages(&self, player_id: PlayerID) -> Vec<DirectMessage> {
+        self.offline_message_map.remove(&player_id).map(|(_, queue)| queue.into()).unwrap_or_default()
+    }
+
+    /* LOBBY CHAT FUNCTIONS */
+    pub fn add_lobby_message(&self, lobby_id: LobbyID, from: PlayerSummary, message: String) {
+        let mut history = self.lobby_chat_map.entry(lobby_id).or_default();
+        history.push_back(LobbyChatMessage { from, message, sent_at: SystemTime::now() });
+        while history.len() > self.server_params.chat.lobby_history {
+            history.pop_front();
+        }
+    }
+
+    pub fn get_lobby_chat(&self, lobby_id: LobbyID) -> Vec<LobbyChatMessage> {
+        self.lobby_chat_map.get(&lobby_id).map(|history| history.iter().cloned().collect()).unwrap_or_default()
+    }
+
+    /* CHANNEL FUNCTIONS */
+    // Returns false if a channel with the same name exists
+    pub fn add_channel(&self, channel: Channel) -> bool {
+        match self.channel_map.entry(channel.name.clone()) {
+            Entry::Occupied(_) => false,
+            Entry::Vacant(entry) => {
+                entry.insert(channel);
+                true
+            }
+        }
+    }
+
+    pub fn get_channel(&self, name: &str) -> Option<Channel> {
+        self.channel_map.get(name).map(|channel| channel.clone())
+    }
+
+    pub fn set_channel(&self, channel: Channel) {
+        self.channel_map.insert(channel.name.clone(), channel);
+    }
+
+    pub fn get_channels(&self) -> Vec<Channel> {
+        self.channel_map.iter().map(|entry| entry.value().clone()).collect()
+    }
+
+    // Returns the members the channel had
+    pub fn remove_channel(&self, name: &str) -> Option<Vec<PlayerID>> {
+        self.channel_map.remove(name)?;
+        let members: Vec<PlayerID> = self.channel_member_map.remove(name).map(|(_, members)| members.into_iter().collect()).unwrap_or_default();
+        for member_id in members.iter() {
+            if let Some(mut channels) = self.player_channel_map.get_mut(member_id) {
+                channels.remove(name);
+            }
+        }
+        Some(members)
+    }
+
+    pub fn add_channel_member(&self, name: &str, player_id: PlayerID) -> bool {
+        if !self.channel_member_map.entry(name.to_string()).or_default().insert(player_id) {
+            return false;
+        }
+        self.player_channel_map.entry(player_id).or_default().insert(name.to_string());
+        true
+    }
+
+    // Custom channels are deleted once their last member leaves
+    pub fn remove_channel_member(&self, name: &str, player_id: PlayerID) -> bool {
+        let Some(mut members) = self.channel_member_map.get_mut(name) else {
+            return false;
+        };
+        if !members.remove(&player_id) {
+            return false;
+        }
+        let empty = members.is_empty();
+        drop(members);
+        if let Some(mut channels) = self.player_channel_map.get_mut(&player_id) {
+            channels.remove(name);
+        }
+        if empty && self.get_channel(name).is_some_and(|channel| channel.kind == ChannelKind::Custom) {
+            self.channel_map.remove(name);
+            self.channel_member_map.remove(name);
+        }
+        true
+    }
+
+    pub fn is_channel_member(&self, name: &str, player_id: PlayerID) -> bool {
+        self.channel_member_map.get(name).is_some_and(|members| members.contains(&player_id))
+    }
+
+    pub fn get_channel_members(&self, name: &str) -> Vec<PlayerID> {
+        self.channel_member_map.get(name).map(|members| members.iter().copied().collect()).unwrap_or_default()
+    }
+
+    fn leave_all_channels(&self, player_id: PlayerID) {
+        let channels = self.player_channel_map.remove(&player_id).map(|(_, channels)| channels).unwrap_or_default();
+        for name in channels {
+            self.remove_channel_member(&name, player_id);
+        }
+    }
+
+    /* MODERATION FUNCTIONS */
+    pub fn mute_player(&self, lobby_id: LobbyID, player_id: PlayerID, until: SystemTime) {
+        self.lobby_mute_map.insert((lobby_id, player_id), until);
+    }
+
+    pub fn unmute_player(&self, lobby_id: LobbyID, player_id: PlayerID) -> bool {
+        self.lobby_mute_map.remove(&(lobby_id, player_id)).is_some()
+    }
+
+    // Mute end if the player is still muted. Expired mutes are removed
+    pub fn get_mute(&self, lobby_id: LobbyID, player_id: PlayerID) -> Option<SystemTime> {
+        let until = *self.lobby_mute_map.get(&(lobby_id, player_id))?;
+        if until <= SystemTime::now() {
+            self.lobby_mute_map.remove(&(lobby_id, player_id));
+            return None;
+        }
+        Some(until)
+    }
+
+    pub fn add_recent_message(&self, player_id: PlayerID, kind: MessageKind, message: String) {
+        let mut recent = self.recent_message_map.entry(player_id).or_default();
+        recent.push_back(ReportedMessage { kind, message, sent_at: SystemTime::now() });
+        while recent.len() > self.server_params.chat.report_context {
+            recent.pop_front();
+        }
+    }
+
+    pub fn get_recent_messages(&self, player_id: PlayerID) -> Vec<ReportedMessage> {
+        self.recent_message_map.get(&player_id).map(|recent| recent.iter().cloned().collect()).unwrap_or_default()
+    }
+
+    // Returns false if the reporter already has an open report against the same player
+    pub fn add_report(&self, report: Report) -> bool {
+        let duplicate = self.report_map.iter().any(|entry| entry.reporter == report.reporter && entry.reported == report.reported);
+        if duplicate {
+            return false;
+        }
+        self.report_map.insert(report.report_id, report);
+        true
+    }
+
+    pub fn get_reports(&self) -> Vec<Report> {
+        let mut reports: Vec<Report> = self.report_map.iter().map(|entry| entry.value().clone()).collect();
+        reports.sort_by_key(|report| report.created_at);
+        reports
+    }
+
+    pub fn remove_report(&self, report_id: Uuid) -> Option<Report> {
+        self.report_map.remove(&report_id).map(|(_, report)| report)
+    }
+
+    /* BLOCK FUNCTIONS */
+    pub fn block_player(&self, player_id: PlayerID, blocked_id: PlayerID) -> bool {
+        self.block_map.entry(player_id).or_default().insert(blocked_id)
+    }
+
+    pub fn unblock_player(&self, player_id: PlayerID, blocked_id: PlayerID) -> bool {
+        self.block_map.get_mut(&player_id).is_some_and(|mut blocked| blocked.remove(&blocked_id))
+    }
+
+    pub fn is_blocked(&self, player_id: PlayerID, sender_id: PlayerID) -> bool {
+        self.block_map.get(&player_id).is_some_and(|blocked| blocked.contains(&sender_id))
+    }
+
+    /* PRESENCE FUNCTIONS */
+    fn mark_presence_dirty(&self, player_list: &[PlayerID]) {
+        for player_id in player_list {
+            self.presence_dirty.insert(*player_id);
+        }
+    }
+
+    pub fn take_presence_dirty(&self) -> Vec<PlayerID> {
+        let players: Vec<PlayerID> = self.presence_dirty.iter().map(|entry| *entry.key()).collect();
+        for player_id in players.iter() {
+            self.presence_dirty.remove(player_id);
+        }
+        players
+    }
+
+    // Presence derived from the player's connection and the status of their lobby
+    pub fn get_presence(&self, player_id: PlayerID) -> Presence {
+        let Some((_, lobby_id)) = self.get_player_info(player_id) else {
+            return Presence::Offline
+        };
+        let Some(lobby) = lobby_id.and_then(|lobby_id| self.get_lobby(self.get_region_lobby(&lobby_id)?, lobby_id)) else {
+            return Presence::Online
+        };
+        match lobby.status {
+            LobbyStatus::Idle => Presence::InLobby,
+            LobbyStatus::Queueing | LobbyStatus::ReadyCheck => Presence::Queueing,
+            LobbyStatus::Ingame => Presence::InGame
+        }
+    }
+
+    // Stores the player's presence and returns whether it differs from the last one stored
+    pub fn set_presence(&self, player_id: PlayerID, presence: Presence) -> bool {
+        self.presence_map.insert(player_id, presence).unwrap_or(Presence::Offline) != presence
+    }
+
+    /* LEADERBOARD FUNCTIONS */
+    pub fn add_leaderboard_player(&self, region: Region, mode: GameMode, player_id: PlayerID) {
+        let player = self.get_player_info(player_id).map(|(player, _)| player).or_else(|| self.get_account_player(player_id)); // Account may be offline
+        let Some(rating) = player.map(|player| player.rating) else {
+            return
+        };
+        let mut boards = self.player_leaderboard_map.entry(player_id).or_default();
+        if boards.contains(&(region.clone(), mode)) {
+            return
+        }
+        boards.push((region.clone(), mode));
+        self.leaderboard_map.entry((region, mode)).or_default().insert((Reverse(rating), player_id));
+    }
+
+    fn move_leaderboard_entries(&self, player_id: PlayerID, old_rating: usize, new_rating: usize) {
+        let Some(boards) = self.player_leaderboard_map.get(&player_id) else {
+            return
+        };
+        for board in boards.iter() {
+            if let Some(mut entries) = self.leaderboard_map.get_mut(board) {
+                entries.remove(&(Reverse(old_rating), player_id));
+                entries.insert((Reverse(new_rating), player_id));
+            }
+        }
+    }
+
+    fn remove_leaderboard_entries(&self, player_id: PlayerID, rating: usize) {
+        let Some((_, boards)) = self.player_leaderboard_map.remove(&player_id) else {
+            return
+        };
+        for board in boards.iter() {
+            if let Some(mut entries) = self.leaderboard_map.get_mut(board) {
+                entries.remove(&(Reverse(rating), player_id));
+            }
+        }
+    }
+
+    // Page of the leaderboard starting at offset (0 is the top player), and the total number of players on it
+    pub fn get_leaderboard(&self, region: Region, mode: GameMode, offset: usize, limit: usize) -> (Vec<LeaderboardEntry>, usize) {
+        let Some(entries) = self.leaderboard_map.get(&(region, mode)) else {
+            return (Vec::new(), 0)
+        };
+        let page = entries.iter().enumerate().skip(offset).take(limit)
+            .map(|(index, (Reverse(rating), player_id))| LeaderboardEntry { position: index + 1, player_id: *player_id, rating: *rating })
+            .collect();
+        (page, entries.len())
+    }
+
+    pub fn get_leaderboard_position(&self, region: Region, mode: GameMode, player_id: PlayerID) -> Option<LeaderboardEntry> {
+        let entries = self.leaderboard_map.get(&(region, mode))?;
+        let (player, _) = self.get_player_info(player_id)?;
+        let key = (Reverse(player.rating), player_id);
+        if !entries.contains(&key) {
+            return None
+        }
+        let ahead = entries.range(..key).count();
+        Some(LeaderboardEntry { position: ahead + 1, player_id, rating: player.rating })
+    }
+
+    /* QUEUE STATUS FUNCTIONS */
+    pub fn set_queue_time(&self, lobby_id: LobbyID, queued_at: SystemTime) {
+        self.queue_time_map.insert(lobby_id, queued_at);
+    }
+
+    pub fn get_queue_time(&self, lobby_id: LobbyID) -> Option<SystemTime> {
+        self.queue_time_map.get(&lobby_id).map(|entry| *entry.value())
+    }
+
+    pub fn remove_queue_time(&self, lobby_id: LobbyID) {
+        self.queue_time_map.remove(&lobby_id);
+    }
+
+    pub fn get_queued_lobby_ids(&self) -> Vec<LobbyID> {
+        self.queue_time_map.iter().map(|entry| *entry.key()).collect()
+    }
+
+    // Position of the lobby in its queue, and the total number of lobbies in that queue
+    pub fn get_queue_position(&self, region: Region, mode: GameMode, lobby_id: LobbyID) -> Option<(usize, usize)> {
+        if let Some(lobbies) = self.party_queue_map.get(&(region.clone(), mode)) { // Partial lobbies wait in the party queue
+            if let Some(index) = lobbies.iter().position(|l| *l == lobby_id) {
+                return Some((index + 1, lobbies.len()))
+            }
+        }
+        match mode {
+            GameMode::Casual => { // Casual queue is already in arrival order
+                let lobbies = self.casual_queue_map.get(&region)?;
+                let index = lobbies.iter().position(|lobby| lobby.lobby_id == lobby_id)?;
+                Some((index + 1, lobbies.len()))
+            },
+            GameMode::Competitive => { // Competitive queue is ordered by rating, so order by time queued instead
+                let lobbies = self.competitive_queue_map.get(&region)?;
+                let queued_at = self.get_queue_time(lobby_id)?;
+                if !lobbies.iter().any(|lobby| lobby.lobby_id == lobby_id) {
+                    return None
+                }
+                let ahead = lobbies.iter()
+                    .filter(|lobby| self.get_queue_time(lobby.lobby_id).is_some_and(|time| time < queued_at))
+                    .count();
+                Some((ahead + 1, lobbies.len()))
+            }
+        }
+    }
+
+    pub fn add_wait_time(&self, region: Region, mode: GameMode, wait: Duration) {
+        let mut history = self.wait_history_map.entry((region, mode)).or_default();
+        history.push_back(wait);
+        if history.len() > WAIT_HISTORY_SIZE {
+            history.pop_front();
+        }
+    }
+
+    pub fn get_average_wait_time(&self, region: Region, mode: GameMode) -> Option<Duration> {
+        let history = self.wait_history_map.get(&(region, mode))?;
+        if history.is_empty() {
+            return None
+        }
+        Some(history.iter().sum::<Duration>() / history.len() as u32)
+    }
+
     /* MISCELLANEOUS FUNCTIONS */
 
     pub fn lobby_size(&self) -> usize {
         self.server_params.player_count
     }
 
+    pub fn initial_rating(&self) -> usize {
+        self.server_params.initial_rating
+    }
+
+    pub fn rating_model(&self) -> RatingModel {
+        self.server_params.rating_model
+    }
+
+    pub fn ready_check(&self) -> Option<Duration> {
+        self.server_params.ready_check
+    }
+
+    pub fn match_expiry(&self) -> Option<Duration> {
+        self.server_params.match_expiry
+    }
+
+    pub fn backfill(&self) -> bool {
+        self.server_params.backfill
+    }
+
+    pub fn auth_provider(&self) -> Option<Arc<dyn AuthProvider>> {
+        self.server_params.auth.clone()
+    }
+
+    pub fn authenticator(&self) -> Option<Arc<dyn Authenticator>> {
+        self.server_params.authenticator.clone()
+    }
+
+    pub fn profile_rules(&self) -> ProfileRules {
+        self.server_params.profiles.clone()
+    }
+
+    pub fn chat_params(&self) -> ChatParams {
+        self.server_params.chat.clone()
+    }
+
+    pub fn message_filters(&self) -> Vec<Arc<dyn MessageFilter>> {
+        self.server_params.chat.filters.clone()
+    }
+
+    pub fn block_lobby_joins(&self) -> bool {
+        self.server_params.block_lobby_joins
+    }
+
+    pub fn penalty_params(&self) -> PenaltyParams {
+        self.server_params.penalties.clone()
+    }
+
+    pub fn queue_status_interval(&self) -> Option<Duration> {
+        self.server_params.queue_status_interval
+    }
+
+    pub fn is_valid_region(&self, region: &Region) -> bool {
+        self.server_params.regions.contains(region)
+    }
+
+    pub fn role_composition(&self) -> Vec<(String, usize)> {
+        self.server_params.role_composition.clone()
+    }
+
+    pub fn ranked_params(&self) -> RankedParams {
+        self.server_params.ranked.clone()
+    }
+
+    // Falls back to the regions lobbies can be created in
+    pub fn served_regions(&self) -> Vec<Region> {
+        if self.server_params.served_regions.is_empty() {
+            return self.server_params.regions.clone()
+        }
+        self.server_params.served_regions.clone()
+    }
+
 }
\ No newline at end of file